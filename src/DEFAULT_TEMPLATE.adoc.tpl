@@ -0,0 +1,8 @@
+= {{crate}}
+
+{{readme}}
+{% if license != none %}
+== License
+
+{{license}}
+{% endif %}
@@ -0,0 +1,11 @@
+{{crate | underline("=")}}
+{{crate}}
+{{crate | underline("=")}}
+
+{{readme}}
+{% if license != none %}
+License
+=======
+
+{{license}}
+{% endif %}
@@ -0,0 +1,10 @@
+{{crate}}
+{{crate | underline("=")}}
+
+{{readme}}
+{% if license != none %}
+License
+=======
+
+{{license}}
+{% endif %}
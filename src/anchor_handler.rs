@@ -1,21 +1,68 @@
+use crate::convert::{LinkChange, LinkDiagnostic};
+use crate::flavor::Flavor;
 use html2md::{StructuredPrinter, TagHandler, TagHandlerFactory};
 use markup5ever_rcdom::{Handle, NodeData};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::{cell::RefCell, rc::Rc};
 use url::Url;
 
 static STD_DOCS_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^https://doc.rust-lang.org/([0-9]\.[0-9]{1,5}\.[0-9]{1,5}|stable|beta|nightly)/(core|alloc|std)/(.*)$").unwrap()
 });
 
+/// Resolve a link, other than an in-page `#fragment` one, to the URL it should have in the README
+///
+/// Returns the URL, unless the link has to be dropped, and what changed about the link.
+pub(crate) fn resolve_link(
+    href: &str,
+    base_url: Option<&Url>,
+    unpin_std_docs: bool,
+) -> (Option<String>, Option<LinkChange>) {
+    let mut url = href.to_owned();
+    if unpin_std_docs {
+        if let Some(captures) = STD_DOCS_REGEX.captures(&url) {
+            let captures: [&str; 3] = captures.extract().1;
+            url = format!("https://doc.rust-lang.org/{}/{}", captures[1], captures[2]);
+        }
+    }
+
+    if url.starts_with("https://") || url.starts_with("http://") {
+        let change = (url != href).then(|| LinkChange::Rewritten(url.clone()));
+        return (Some(url), change);
+    }
+
+    match base_url {
+        Some(base_url) => match base_url.join(&url) {
+            Ok(url) => (
+                Some(url.to_string()),
+                Some(LinkChange::Rewritten(url.to_string())),
+            ),
+            Err(err) => (
+                None,
+                Some(LinkChange::Dropped(format!("invalid URL ({err})"))),
+            ),
+        },
+        None => (
+            None,
+            Some(LinkChange::Dropped("relative link without base URL".into())),
+        ),
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub(crate) struct AnchorHandler {
     base_url: Option<Url>,
     start_pos: usize,
     // This is not necessarily a full URL - can be an anchor, for example
     url: Option<String>,
+    // The link's `href` as written by rustdoc
+    href: String,
+    change: Option<LinkChange>,
+    links: Rc<RefCell<Vec<LinkDiagnostic>>>,
     skip_descendents: bool,
     unpin_std_docs: bool,
+    flavor: Flavor,
 }
 
 impl TagHandler for AnchorHandler {
@@ -23,16 +70,16 @@ impl TagHandler for AnchorHandler {
         self.start_pos = printer.data.len();
 
         // try to extract a hyperlink
-        let mut url = match tag.data {
+        let url = match tag.data {
             NodeData::Element { ref attrs, .. } => {
                 let attrs = attrs.borrow();
 
-                // Ignore tooltips
+                // Ignore tooltips and rustdoc's `§` heading anchors
                 if let Some(class) = attrs
                     .iter()
                     .find(|attr| attr.name.local.to_string() == "class")
                 {
-                    if class.value == "tooltip".into() {
+                    if class.value == "tooltip".into() || class.value == "doc-anchor".into() {
                         self.skip_descendents = true;
                         return;
                     }
@@ -49,6 +96,12 @@ impl TagHandler for AnchorHandler {
             _ => String::new(),
         };
 
+        // Drop footnote back-references, along with their "↩" text
+        if url.starts_with("#fnref") {
+            self.skip_descendents = true;
+            return;
+        }
+
         // Don't convert header links
         let immediate_parent = printer.parent_chain.last().unwrap().to_owned();
         if ["h1", "h2", "h3", "h4", "h5", "h6"].contains(&immediate_parent.as_str())
@@ -57,27 +110,25 @@ impl TagHandler for AnchorHandler {
             return;
         }
 
-        if self.unpin_std_docs {
-            if let Some(captures) = STD_DOCS_REGEX.captures(&url) {
-                let captures: [&str; 3] = captures.extract().1;
-                url = format!("https://doc.rust-lang.org/{}/{}", captures[1], captures[2]);
-            }
+        self.href = url.clone();
+        if url.starts_with('#') && !self.flavor.heading_anchors() {
+            self.change = Some(LinkChange::Dropped(format!(
+                "{} has no heading anchors",
+                self.flavor
+            )));
+            return;
         }
 
-        if url.starts_with("https://") || url.starts_with("http://") || url.starts_with('#') {
+        if url.starts_with('#') {
+            // Whether this needs rewriting is only known once all headings have been seen
+            self.change = Some(LinkChange::Unresolved);
             self.url = Some(url);
             return;
         }
 
-        self.url = self
-            .base_url
-            .as_ref()
-            .map(|base_url| base_url.join(&url).map(|url| url.to_string()))
-            .transpose()
-            .unwrap_or_else(|err| {
-                eprintln!("Error parsing URL: {err}");
-                None
-            })
+        let (url, change) = resolve_link(&url, self.base_url.as_ref(), self.unpin_std_docs);
+        self.url = url;
+        self.change = change;
     }
 
     fn skip_descendants(&self) -> bool {
@@ -85,6 +136,18 @@ impl TagHandler for AnchorHandler {
     }
 
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if let Some(change) = self.change.take() {
+            let text = printer.data[self.start_pos..]
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            self.links.borrow_mut().push(LinkDiagnostic {
+                text,
+                href: self.href.clone(),
+                change,
+            });
+        }
+
         // add braces around already present text, put an url afterwards
         if let Some(url) = &self.url {
             printer.insert_str(self.start_pos, "[");
@@ -97,6 +160,8 @@ impl TagHandler for AnchorHandler {
 pub(crate) struct AnchorHandlerFactory {
     pub base_url: Option<Url>,
     pub unpin_std_docs: bool,
+    pub links: Rc<RefCell<Vec<LinkDiagnostic>>>,
+    pub flavor: Flavor,
 }
 
 impl TagHandlerFactory for AnchorHandlerFactory {
@@ -104,6 +169,8 @@ impl TagHandlerFactory for AnchorHandlerFactory {
         Box::new(AnchorHandler {
             base_url: self.base_url.clone(),
             unpin_std_docs: self.unpin_std_docs,
+            links: self.links.clone(),
+            flavor: self.flavor,
             ..Default::default()
         })
     }
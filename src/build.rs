@@ -0,0 +1,94 @@
+//! Build documentation with `cargo doc`
+use anyhow::{anyhow, bail, Result};
+use cargo_metadata::Message;
+use std::{
+    collections::HashMap,
+    env,
+    io::BufReader,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Documentation generated by `cargo doc`
+#[derive(Clone, Debug, Default)]
+pub struct Docs {
+    // Generated `index.html` files, keyed by target name
+    index_paths: HashMap<String, PathBuf>,
+}
+
+impl Docs {
+    /// Run `cargo doc --no-deps` with extra `args`, writing to `target_dir`
+    ///
+    /// Documentation is built with `--cfg doc2readme`, so it shouldn't go in the usual target
+    /// directory, where it would replace the regular documentation and force rebuilds. Cargo
+    /// reports where it wrote each crate's documentation, so this still respects `--target` in
+    /// `args`. `--target-dir` isn't allowed, since `target_dir` takes its place.
+    pub fn build(args: &[String], target_dir: &Path) -> Result<Self> {
+        if args
+            .iter()
+            .any(|arg| arg == "--target-dir" || arg.starts_with("--target-dir="))
+        {
+            bail!(
+                "`--target-dir` can't be passed to `cargo doc`, documentation is built in '{}'",
+                target_dir.display()
+            );
+        }
+
+        let mut command = Command::new("cargo");
+        command.env("CARGO_TARGET_DIR", target_dir);
+        // Flags from the environment replace `build.rustdocflags`, so add to whichever cargo uses
+        if let Ok(mut rustdocflags) = env::var("CARGO_ENCODED_RUSTDOCFLAGS") {
+            if !rustdocflags.is_empty() {
+                rustdocflags.push('\x1f');
+            }
+            rustdocflags.push_str("--cfg\x1fdoc2readme");
+            command.env("CARGO_ENCODED_RUSTDOCFLAGS", rustdocflags);
+        } else if let Ok(mut rustdocflags) = env::var("RUSTDOCFLAGS") {
+            rustdocflags.push_str(" --cfg doc2readme");
+            command.env("RUSTDOCFLAGS", rustdocflags.trim_start());
+        } else {
+            // Arrays from `--config` are appended to the ones from config files
+            command
+                .arg("--config")
+                .arg(r#"build.rustdocflags=["--cfg", "doc2readme"]"#);
+        }
+
+        let mut child = command
+            .arg("doc")
+            .arg("--no-deps")
+            .arg("--message-format=json-render-diagnostics")
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Could not capture `cargo doc` output"))?;
+
+        let mut index_paths = HashMap::new();
+        for message in Message::parse_stream(BufReader::new(stdout)) {
+            if let Message::CompilerArtifact(artifact) = message? {
+                let index_path = artifact
+                    .filenames
+                    .into_iter()
+                    .find(|path| path.file_name() == Some("index.html"));
+                if let Some(index_path) = index_path {
+                    index_paths.insert(artifact.target.name, index_path.into());
+                }
+            }
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            bail!("`cargo doc` failed ({status})");
+        }
+
+        Ok(Self { index_paths })
+    }
+
+    /// Path to the generated `index.html` for a target, if `cargo doc` reported one
+    pub fn index_path(&self, target_name: &str) -> Option<&Path> {
+        self.index_paths.get(target_name).map(PathBuf::as_path)
+    }
+}
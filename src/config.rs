@@ -0,0 +1,202 @@
+//! Configuration from `[package.metadata.doc2readme]` and `[workspace.metadata.doc2readme]`
+use crate::{flavor::Flavor, format::Format, manifest::TargetSelection};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Options that can be set in a manifest instead of on the command line
+///
+/// All fields are optional, so configurations can be layered with [`Config::or`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Base URL for relative links
+    pub base_url: Option<String>,
+    /// Base URL for relative image sources, if different from `base_url`
+    pub asset_base_url: Option<String>,
+    /// Use the crate's docs.rs page as the base URL if `base_url` isn't set
+    pub docs_rs: Option<bool>,
+    /// Path to the template
+    pub template: Option<PathBuf>,
+    /// Output path
+    pub output: Option<PathBuf>,
+    /// Document the library target
+    pub lib: Option<bool>,
+    /// Document the binary target with this name
+    pub bin: Option<String>,
+    /// Path of the item to document instead of the crate root, e.g. `module::Type`
+    pub item: Option<String>,
+    /// Unpin the version for `std`/`core`/`alloc` docs links
+    pub unpin_std_docs: Option<bool>,
+    /// Render warning blocks as plain blockquotes instead of GitHub alerts
+    pub plain_warnings: Option<bool>,
+    /// Replace typographic quotes, dashes and ellipses with ASCII
+    pub ascii_punctuation: Option<bool>,
+    /// Markdown dialect to target
+    pub flavor: Option<Flavor>,
+    /// Markup language to write
+    pub format: Option<Format>,
+    /// Fail if any links are dropped during conversion
+    pub deny_dropped_links: Option<bool>,
+    /// Headings of sections to leave out of the README
+    pub exclude_sections: Option<Vec<String>>,
+    /// Arguments to pass to `cargo doc`
+    pub rustdoc_args: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Extract the `doc2readme` table from a package's or workspace's `metadata` table
+    pub fn from_metadata(metadata: &serde_json::Value) -> Result<Self> {
+        metadata
+            .get("doc2readme")
+            .map(|config| {
+                Self::deserialize(config).context("Invalid `doc2readme` metadata in Cargo.toml")
+            })
+            .unwrap_or_else(|| Ok(Default::default()))
+    }
+
+    /// Use values from `fallback` for any options not set in `self`
+    ///
+    /// `lib` and `bin` are one choice of target, so they're taken together from whichever
+    /// configuration sets either.
+    pub fn or(self, fallback: Self) -> Self {
+        let (lib, bin) = if self.lib.is_some() || self.bin.is_some() {
+            (self.lib, self.bin)
+        } else {
+            (fallback.lib, fallback.bin)
+        };
+        Self {
+            base_url: self.base_url.or(fallback.base_url),
+            asset_base_url: self.asset_base_url.or(fallback.asset_base_url),
+            docs_rs: self.docs_rs.or(fallback.docs_rs),
+            template: self.template.or(fallback.template),
+            output: self.output.or(fallback.output),
+            lib,
+            bin,
+            item: self.item.or(fallback.item),
+            unpin_std_docs: self.unpin_std_docs.or(fallback.unpin_std_docs),
+            plain_warnings: self.plain_warnings.or(fallback.plain_warnings),
+            ascii_punctuation: self.ascii_punctuation.or(fallback.ascii_punctuation),
+            flavor: self.flavor.or(fallback.flavor),
+            format: self.format.or(fallback.format),
+            deny_dropped_links: self.deny_dropped_links.or(fallback.deny_dropped_links),
+            exclude_sections: self.exclude_sections.or(fallback.exclude_sections),
+            rustdoc_args: self.rustdoc_args.or(fallback.rustdoc_args),
+        }
+    }
+
+    /// The target selected by `lib` or `bin`
+    pub fn target(&self) -> Result<TargetSelection> {
+        match (self.lib.unwrap_or_default(), &self.bin) {
+            (false, None) => Ok(TargetSelection::Default),
+            (true, None) => Ok(TargetSelection::Lib),
+            (false, Some(bin)) => Ok(TargetSelection::Bin(bin.clone())),
+            (true, Some(_)) => bail!("Cannot select both a lib and a bin target"),
+        }
+    }
+
+    /// Resolve relative paths against `dir`
+    pub fn relative_to(self, dir: &Path) -> Self {
+        Self {
+            template: self.template.map(|path| dir.join(path)),
+            output: self.output.map(|path| dir.join(path)),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn from_metadata() {
+        let config = Config::from_metadata(&json!({
+            "doc2readme": {
+                "base-url": "https://dagans.dev/",
+                "unpin-std-docs": true,
+                "flavor": "crates-io",
+                "exclude-sections": ["Todo"],
+            },
+            "some-other-tool": {},
+        }))
+        .unwrap();
+        assert_eq!(config.base_url.as_deref(), Some("https://dagans.dev/"));
+        assert_eq!(config.unpin_std_docs, Some(true));
+        assert_eq!(config.flavor, Some(Flavor::CratesIo));
+        assert_eq!(config.exclude_sections, Some(vec!["Todo".to_owned()]));
+        assert_eq!(config.template, None);
+
+        let config = Config::from_metadata(&serde_json::Value::Null).unwrap();
+        assert_eq!(config.base_url, None);
+
+        assert!(Config::from_metadata(&json!({"doc2readme": {"base-ulr": "oops"}})).is_err());
+    }
+
+    #[test]
+    fn layering() {
+        let cli = Config {
+            output: Some("CLI.md".into()),
+            ..Default::default()
+        };
+        let package = Config {
+            output: Some("PACKAGE.md".into()),
+            template: Some("PACKAGE.tpl".into()),
+            ..Default::default()
+        };
+        let workspace = Config {
+            template: Some("WORKSPACE.tpl".into()),
+            unpin_std_docs: Some(true),
+            ..Default::default()
+        };
+
+        let config = cli.or(package.or(workspace));
+        assert_eq!(config.output, Some("CLI.md".into()));
+        assert_eq!(config.template, Some("PACKAGE.tpl".into()));
+        assert_eq!(config.unpin_std_docs, Some(true));
+        assert_eq!(config.base_url, None);
+    }
+
+    #[test]
+    fn target() {
+        let lib = Config {
+            lib: Some(true),
+            ..Default::default()
+        };
+        let bin = Config {
+            bin: Some("tool".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            TargetSelection::Default,
+            Config::default().target().unwrap()
+        );
+        assert_eq!(TargetSelection::Lib, lib.clone().target().unwrap());
+        assert_eq!(
+            TargetSelection::Bin("tool".into()),
+            bin.clone().target().unwrap()
+        );
+
+        // A higher layer replaces the choice of target instead of adding to it
+        assert_eq!(
+            TargetSelection::Lib,
+            lib.clone().or(bin.clone()).target().unwrap()
+        );
+        assert_eq!(
+            TargetSelection::Bin("tool".into()),
+            bin.clone().or(lib.clone()).target().unwrap()
+        );
+        assert_eq!(
+            TargetSelection::Lib,
+            Config::default().or(lib).target().unwrap()
+        );
+
+        let both = Config {
+            lib: Some(true),
+            ..bin
+        };
+        assert!(both.target().is_err());
+    }
+}
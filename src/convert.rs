@@ -1,59 +1,493 @@
+//! Convert rustdoc HTML to Markdown
 use crate::anchor_handler::AnchorHandlerFactory;
 use crate::code_handler::CodeHandlerFactory;
+use crate::exclude::remove_excluded;
+use crate::flavor::Flavor;
+use crate::footnote_handler::FootnoteHandlerFactory;
+use crate::format::Format;
 use crate::header_handler::HeaderHandlerFactory;
+use crate::image_handler::ImageHandlerFactory;
+use crate::markup::{asciidoc_escape, Renderer};
+use crate::table_handler::{HtmlLinks, TableHandlerFactory};
+use crate::underline_handler::UnderlineHandlerFactory;
+use crate::warning_handler::WarningHandlerFactory;
 use anyhow::{anyhow, Result};
 use html2md::TagHandlerFactory;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
 use scraper::{Html, Selector};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
 use url::Url;
 
+static FRAGMENT_LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\]\(#([^)\s]*)\)").unwrap());
+static LIST_BULLET_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^((?:> ?)*\s*)[-+] ").unwrap());
+pub(crate) static MARKDOWN_ESCAPE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[\\`*_\[\]<]").unwrap());
+
+// Written before each heading while converting, to split the README into sections at. Headings
+// can end up quoted or indented, so they can't be told apart from the text alone
+pub(crate) const SECTION_MARK: char = '\u{E000}';
+
+/// Conversion options
 #[derive(Clone, Debug, Default)]
 pub struct Options {
-    // Base URL used to convert relative links to absolute ones
+    /// Base URL used to convert relative links to absolute ones
     pub base_url: Option<String>,
-    // Unpin `std` documentation versions?
+    /// Base URL used to resolve relative image sources, instead of `base_url`
+    pub asset_base_url: Option<String>,
+    /// Unpin `std` documentation versions?
     pub unpin_std_docs: bool,
+    /// Render warning blocks as plain blockquotes instead of GitHub alerts
+    pub plain_warnings: bool,
+    /// Markdown dialect to target
+    pub flavor: Flavor,
+    /// Markup language to write
+    pub format: Format,
+    /// Replace the typographic quotes, dashes and ellipses rustdoc produces with ASCII
+    pub ascii_punctuation: bool,
+    /// Headings of sections to leave out
+    pub exclude_sections: Vec<String>,
 }
 
+/// What happened to a link during conversion
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkChange {
+    /// The link was removed, leaving only its text, for the given reason
+    Dropped(String),
+    /// The link now points to the given URL
+    Rewritten(String),
+    /// The link points to an in-page anchor that doesn't match any heading, and was kept as is
+    Unresolved,
+}
+
+/// A link that was dropped or changed during conversion
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkDiagnostic {
+    /// The link text, as Markdown
+    pub text: String,
+    /// The link's original `href`
+    pub href: String,
+    /// What happened to the link
+    pub change: LinkChange,
+}
+
+impl fmt::Display for LinkDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { text, href, change } = self;
+        match change {
+            LinkChange::Dropped(reason) => write!(f, "dropped [{text}]({href}): {reason}"),
+            LinkChange::Rewritten(url) => write!(f, "rewrote [{text}]({href}) to {url}"),
+            LinkChange::Unresolved => {
+                write!(f, "kept [{text}]({href}): does not match any heading")
+            }
+        }
+    }
+}
+
+/// A heading in the generated README
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    /// The heading level, from 1 to 6
+    pub level: usize,
+    /// The heading text, without markup
+    pub text: String,
+    /// The in-page anchor the heading can be linked to with, if the format has any
+    pub anchor: Option<String>,
+}
+
+/// A part of the generated README, from a heading up to the next heading of the same or a higher
+/// level
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Section {
+    /// The heading the section starts with, or `None` for the text before the first heading
+    pub heading: Option<Heading>,
+    /// The section's text, including its heading and subsections
+    pub text: String,
+}
+
+/// The result of converting a rustdoc HTML page
+#[derive(Clone, Debug)]
+pub struct Conversion {
+    /// The generated README text, in the requested [`Format`]
+    pub readme: String,
+    /// Every link that was dropped or changed, in document order
+    pub links: Vec<LinkDiagnostic>,
+    /// Every heading, in document order
+    pub headings: Vec<Heading>,
+    /// A table of contents listing [`headings`](Self::headings), in the requested [`Format`]
+    pub toc: String,
+    /// The README split into sections, one for each heading, in document order
+    ///
+    /// Sections include their subsections, so they overlap when headings are nested.
+    pub sections: Vec<Section>,
+}
+
+/// Convert a rustdoc HTML page to Markdown, or another [`Format`]
+///
+/// Only the page's first `.docblock` element (the item's documentation) is converted.
 pub fn html_to_readme(html: &str, options: Options) -> Result<String> {
-    let html = Html::parse_fragment(html);
+    convert_page(html, options).map(|conversion| conversion.readme)
+}
+
+/// Like [`html_to_readme`], but also report which links were dropped or changed
+pub fn convert_page(html: &str, options: Options) -> Result<Conversion> {
+    let mut html = Html::parse_fragment(html);
+    remove_excluded(&mut html, &options.exclude_sections);
     let docblock = html
         .select(&query(".docblock")?)
         .next()
-        .ok_or_else(|| anyhow!("Could not find .docblock element. Is this crate documented?"))?
-        .inner_html();
+        .ok_or_else(|| anyhow!("Could not find .docblock element. Is this crate documented?"))?;
+
+    let base_url = parse_base_url(options.base_url.clone())?;
+    let asset_base_url =
+        parse_base_url(options.asset_base_url.clone())?.or_else(|| base_url.clone());
+
+    if options.format != Format::Markdown {
+        let mut renderer = Renderer::new(&options, base_url, asset_base_url);
+        let (readme, sections) =
+            split_sections(&renderer.render(docblock), &renderer.headings.list);
+        return Ok(Conversion {
+            links: renderer.links,
+            toc: table_of_contents(&renderer.headings.list, options.format),
+            sections,
+            headings: renderer.headings.list,
+            readme,
+        });
+    }
+    let docblock = docblock.inner_html();
 
+    let links = Rc::new(RefCell::new(Vec::new()));
     let mut handlers = HashMap::<String, Box<dyn TagHandlerFactory>>::new();
     handlers.insert(
         String::from("a"),
         Box::new(AnchorHandlerFactory {
-            base_url: options
-                .base_url
-                .map(|mut s| {
-                    // User has no business files as part of the base url, so assume they meant a
-                    // directory
-                    if !s.ends_with('/') {
-                        s.push('/')
-                    };
-
-                    Url::parse(&s)
-                })
-                .transpose()?,
+            base_url: base_url.clone(),
             unpin_std_docs: options.unpin_std_docs,
+            links: links.clone(),
+            flavor: options.flavor,
         }),
     );
-    handlers.insert(String::from("h1"), Box::new(HeaderHandlerFactory));
-    handlers.insert(String::from("h2"), Box::new(HeaderHandlerFactory));
-    handlers.insert(String::from("h3"), Box::new(HeaderHandlerFactory));
-    handlers.insert(String::from("h4"), Box::new(HeaderHandlerFactory));
-    handlers.insert(String::from("h5"), Box::new(HeaderHandlerFactory));
-    handlers.insert(String::from("h6"), Box::new(HeaderHandlerFactory));
+    handlers.insert(
+        String::from("img"),
+        Box::new(ImageHandlerFactory {
+            base_url: asset_base_url.clone(),
+            links: links.clone(),
+        }),
+    );
+    let header_handler = HeaderHandlerFactory::new(options.flavor, options.ascii_punctuation);
+    for tag in ["h1", "h2", "h3", "h4", "h5", "h6"] {
+        handlers.insert(String::from(tag), Box::new(header_handler.clone()));
+    }
     handlers.insert(String::from("code"), Box::new(CodeHandlerFactory));
     handlers.insert(String::from("pre"), Box::new(CodeHandlerFactory));
+    for tag in ["u", "ins"] {
+        handlers.insert(String::from(tag), Box::new(UnderlineHandlerFactory));
+    }
+    let footnote_handler = FootnoteHandlerFactory::new(options.flavor);
+    for tag in ["sup", "ol", "li", "hr"] {
+        handlers.insert(String::from(tag), Box::new(footnote_handler.clone()));
+    }
+    handlers.insert(
+        String::from("div"),
+        Box::new(WarningHandlerFactory {
+            plain: options.plain_warnings || !options.flavor.alerts(),
+            fallback: Rc::new(footnote_handler),
+        }),
+    );
+    let table_handler = TableHandlerFactory::new(HtmlLinks {
+        flavor: options.flavor,
+        base_url,
+        asset_base_url,
+        unpin_std_docs: options.unpin_std_docs,
+        links: links.clone(),
+    });
+    for tag in ["table", "thead", "tbody", "tfoot", "tr", "th", "td"] {
+        handlers.insert(String::from(tag), Box::new(table_handler.clone()));
+    }
+
+    let markdown = normalize(&html2md::parse_html_custom(&docblock, &handlers));
+
+    // rustdoc's heading ids don't always match the anchors the target flavor generates, so point
+    // in-page links at the latter. This is done after conversion since links can precede their
+    // heading
+    let headings = header_handler.headings.borrow();
+    let markdown = map_prose(&markdown, |prose| {
+        FRAGMENT_LINK_REGEX
+            .replace_all(prose, |captures: &Captures| {
+                headings
+                    .find(&captures[1])
+                    .and_then(|heading| heading.anchor.as_ref())
+                    .map_or_else(|| captures[0].to_owned(), |anchor| format!("](#{anchor})"))
+            })
+            .into_owned()
+    });
 
-    let markdown = html2md::parse_html_custom(&docblock, &handlers);
+    let mut links = links.take();
+    links.retain_mut(|link| {
+        if link.change != LinkChange::Unresolved {
+            return true;
+        }
+        let Some(anchor) = headings
+            .find(&link.href[1..])
+            .and_then(|heading| heading.anchor.as_ref())
+        else {
+            return true;
+        };
+        if link.href[1..] == *anchor {
+            return false;
+        }
+        link.change = LinkChange::Rewritten(format!("#{anchor}"));
+        true
+    });
+
+    let markdown = if options.ascii_punctuation {
+        restore_ascii_punctuation(&markdown)
+    } else {
+        markdown
+    };
+
+    let headings = headings.list.clone();
+    let (readme, sections) = split_sections(&markdown, &headings);
+
+    Ok(Conversion {
+        links,
+        toc: table_of_contents(&headings, options.format),
+        sections,
+        headings,
+        readme,
+    })
+}
+
+/// Render a nested list of the headings, linking to them where possible
+fn table_of_contents(headings: &[Heading], format: Format) -> String {
+    let Some(top_level) = headings.iter().map(|heading| heading.level).min() else {
+        return String::new();
+    };
+
+    let items = headings
+        .iter()
+        .map(|heading| {
+            let depth = heading.level - top_level;
+            let indent = "  ".repeat(depth);
+            let text = &heading.text;
+            match format {
+                Format::Markdown => {
+                    let text = MARKDOWN_ESCAPE_REGEX.replace_all(text, "\\$0");
+                    match &heading.anchor {
+                        Some(anchor) => format!("{indent}* [{text}](#{anchor})"),
+                        None => format!("{indent}* {text}"),
+                    }
+                }
+                Format::Rst => {
+                    let text = text
+                        .replace('\\', "\\\\")
+                        .replace('`', "\\`")
+                        .replace('<', "\\<");
+                    // Titles can repeat, so link to the explicit target written before each one
+                    match &heading.anchor {
+                        Some(anchor) => format!("{indent}- `{text} <{anchor}_>`__"),
+                        None => format!("{indent}- {text}"),
+                    }
+                }
+                Format::AsciiDoc => {
+                    let marker = "*".repeat(depth + 1);
+                    let text = text
+                        .chars()
+                        .map(|c| asciidoc_escape(c).map_or_else(|| c.to_string(), str::to_owned))
+                        .collect::<String>();
+                    match &heading.anchor {
+                        Some(anchor) => format!("{marker} <<{anchor},{text}>>"),
+                        None => format!("{marker} {text}"),
+                    }
+                }
+                Format::Text => format!("{indent}- {text}"),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Nested reStructuredText lists have to be set apart by blank lines
+    items.join(if format == Format::Rst { "\n\n" } else { "\n" })
+}
+
+// The ASCII rustdoc's smart punctuation was made from
+pub(crate) fn ascii_punctuation(c: char) -> Option<&'static str> {
+    match c {
+        '‘' | '’' => Some("'"),
+        '“' | '”' => Some("\""),
+        '–' => Some("--"),
+        '—' => Some("---"),
+        '…' => Some("..."),
+        _ => None,
+    }
+}
+
+/// Split the README at the [`SECTION_MARK`]s written before `headings`, returning it without
+/// the marks along with its sections
+fn split_sections(readme: &str, headings: &[Heading]) -> (String, Vec<Section>) {
+    let lines = readme.lines().collect::<Vec<_>>();
+    // Pair each heading with the line it starts on, so sections include any quote or indentation
+    let starts = lines
+        .iter()
+        .enumerate()
+        .flat_map(|(i, line)| line.matches(SECTION_MARK).map(move |_| i))
+        .zip(headings)
+        .collect::<Vec<_>>();
+    let text = |start: usize, end: usize| {
+        lines[start..end]
+            .join("\n")
+            .replace(SECTION_MARK, "")
+            .trim()
+            .to_owned()
+    };
+
+    let mut sections = Vec::with_capacity(starts.len() + 1);
+    let intro = text(0, starts.first().map_or(lines.len(), |(start, _)| *start));
+    if !intro.is_empty() {
+        sections.push(Section {
+            heading: None,
+            text: intro,
+        });
+    }
+    for (i, (start, heading)) in starts.iter().enumerate() {
+        let end = starts[i + 1..]
+            .iter()
+            .find(|(_, next)| next.level <= heading.level)
+            .map_or(lines.len(), |(end, _)| *end);
+        sections.push(Section {
+            heading: Some((*heading).clone()),
+            text: text(*start, end),
+        });
+    }
+    (readme.replace(SECTION_MARK, ""), sections)
+}
+
+// Undo rustdoc's smart punctuation, leaving code blocks and code spans alone
+fn restore_ascii_punctuation(markdown: &str) -> String {
+    map_prose(markdown, replace_punctuation)
+}
+
+// Undo rustdoc's smart punctuation in text without any markup
+pub(crate) fn replace_punctuation(text: &str) -> String {
+    text.chars()
+        .map(|c| ascii_punctuation(c).map_or_else(|| c.to_string(), str::to_owned))
+        .collect()
+}
+
+/// Normalize html2md's output, so the README doesn't churn whenever html2md or rustdoc change
+/// the details of their output
+///
+/// Outside code blocks, trailing whitespace is trimmed (hard line breaks are written as a
+/// backslash instead), runs of blank lines are collapsed and list bullets are written as `*`.
+///
+/// Emphasis is left alone: it's always written as `*` and `**`, with underlined and inserted text
+/// written as strong emphasis by [`UnderlineHandler`](crate::underline_handler::UnderlineHandler)
+/// instead of html2md's `__`. html2md escapes underscores in text, so any other underscore is
+/// literal, e.g. in raw HTML or image alt text.
+fn normalize(markdown: &str) -> String {
+    let lines = markdown.lines().collect::<Vec<_>>();
+    let is_blank = |line: &str| line.trim_matches(['>', ' ', '\t']).is_empty();
+    let mut output = Vec::<String>::with_capacity(lines.len());
+    let mut in_code_block = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        if is_fence(line) {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || is_fence(line) {
+            output.push((*line).to_owned());
+            continue;
+        }
+
+        let trimmed = line.trim_end();
+        if is_blank(line) {
+            // Keep the block quote markers, so quoted paragraphs stay apart
+            if output.last().is_some_and(|last| last == trimmed) {
+                continue;
+            }
+            output.push(trimmed.to_owned());
+            continue;
+        }
+
+        let mut line = LIST_BULLET_REGEX.replace(trimmed, "${1}* ").into_owned();
+        // A hard line break at the end of a paragraph would show as a stray backslash
+        if lines[i].ends_with("  ") && lines.get(i + 1).is_some_and(|next| !is_blank(next)) {
+            line.push('\\');
+        }
+        output.push(line);
+    }
 
-    Ok(markdown)
+    output.join("\n")
+}
+
+// Code blocks may be nested in block quotes or footnotes
+fn is_fence(line: &str) -> bool {
+    line.trim_start_matches(['>', ' ']).starts_with("```")
+}
+
+// Apply `f` to the prose in `markdown`, leaving code blocks and code spans alone
+fn map_prose(markdown: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    let mut in_code_block = false;
+    // Length of the backtick run that opened the current code span, if in one
+    let mut code_span: Option<usize> = None;
+
+    for line in markdown.split_inclusive('\n') {
+        if is_fence(line) {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || is_fence(line) || line.trim().is_empty() {
+            // Code spans can't continue past a paragraph
+            code_span = None;
+            output.push_str(line);
+            continue;
+        }
+
+        let mut prose = String::new();
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '`' {
+                let mut run = 1;
+                while chars.next_if_eq(&'`').is_some() {
+                    run += 1;
+                }
+                match code_span {
+                    None => {
+                        output.push_str(&f(&prose));
+                        prose.clear();
+                        code_span = Some(run);
+                    }
+                    Some(open) if open == run => code_span = None,
+                    Some(_) => {}
+                }
+                output.push_str(&"`".repeat(run));
+                continue;
+            }
+            if code_span.is_some() {
+                output.push(c);
+            } else {
+                prose.push(c);
+            }
+        }
+        output.push_str(&f(&prose));
+    }
+
+    output
+}
+
+fn parse_base_url(base_url: Option<String>) -> Result<Option<Url>> {
+    base_url
+        .map(|mut s| {
+            // User has no business files as part of the base url, so assume they meant a
+            // directory
+            if !s.ends_with('/') {
+                s.push('/')
+            };
+
+            Url::parse(&s)
+        })
+        .transpose()
+        .map_err(Into::into)
 }
 
 fn query(selector: impl AsRef<str>) -> Result<Selector> {
@@ -72,6 +506,713 @@ mod tests {
         assert_eq!("```notrust\nhello you!\n```", markdown.trim());
     }
 
+    #[test]
+    fn table() {
+        let markdown = html_to_readme(
+            "<div class='docblock'><table>
+<thead><tr><th>Name</th><th style=\"text-align: center\">Type</th><th style=\"text-align: right\">Size</th></tr></thead>
+<tbody>
+<tr><td><code>a|b</code></td><td><a href='https://dagans.dev'>link</a></td><td>1</td></tr>
+<tr><td>plain</td><td></td><td>2</td></tr>
+</tbody></table></div>",
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "| Name | Type | Size |\n| --- | :-: | --: |\n| `a\\|b` | [link](https://dagans.dev) | 1 |\n| plain | | 2 |",
+            markdown.trim()
+        );
+
+        // CommonMark has no tables, so they're kept as HTML, underscores and all
+        let options = Options {
+            flavor: Flavor::CommonMark,
+            ..Default::default()
+        };
+        let markdown = html_to_readme(
+            "<div class='docblock'><table><tr><td>snake_case</td><td><code>__init__</code></td></tr></table></div>",
+            options,
+        )
+        .unwrap();
+        assert_eq!(
+            "<table><tbody><tr><td>snake_case</td><td><code>__init__</code></td></tr></tbody></table>",
+            markdown.trim()
+        );
+
+        // Links and images in them are resolved like everywhere else
+        let options = Options {
+            flavor: Flavor::CommonMark,
+            base_url: Some(String::from("https://docs.rs/foo/1.0.0/foo")),
+            ..Default::default()
+        };
+        let conversion = convert_page(
+            "<div class='docblock'><table><tr><td><a href='struct.Foo.html'>Foo</a></td>\
+<td><a href='#usage'>usage</a></td><td><img src='logo.png' alt='Logo'></td></tr></table></div>",
+            options,
+        )
+        .unwrap();
+        assert_eq!(
+            "<table><tbody><tr><td><a href=\"https://docs.rs/foo/1.0.0/foo/struct.Foo.html\">Foo</a></td>\
+<td>usage</td><td><img alt=\"Logo\" src=\"https://docs.rs/foo/1.0.0/foo/logo.png\"></td></tr></tbody></table>",
+            conversion.readme.trim()
+        );
+        assert_eq!(
+            vec![
+                LinkDiagnostic {
+                    text: "Foo".into(),
+                    href: "struct.Foo.html".into(),
+                    change: LinkChange::Rewritten(
+                        "https://docs.rs/foo/1.0.0/foo/struct.Foo.html".into()
+                    ),
+                },
+                LinkDiagnostic {
+                    text: "usage".into(),
+                    href: "#usage".into(),
+                    change: LinkChange::Dropped("commonmark has no heading anchors".into()),
+                },
+                LinkDiagnostic {
+                    text: "Logo".into(),
+                    href: "logo.png".into(),
+                    change: LinkChange::Rewritten("https://docs.rs/foo/1.0.0/foo/logo.png".into()),
+                },
+            ],
+            conversion.links
+        );
+    }
+
+    #[test]
+    fn footnotes() {
+        let markdown = html_to_readme(
+            "<div class=\"docblock\"><p>A note<sup id=\"fnref1\"><a href=\"#fn1\">1</a></sup> and x<sup>2</sup>, y<sup><em>n</em></sup>.</p>
+<div class=\"footnotes\"><hr><ol><li id=\"fn1\"><p>The <em>first</em> note.&nbsp;<a href=\"#fnref1\">↩</a></p></li>
+<li id=\"fn2\"><p>Two</p><p>paragraphs.&nbsp;<a href=\"#fnref2\">↩</a></p></li></ol></div>
+<ol><li>Regular list</li></ol></div>",
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "A note[^1] and x<sup>2</sup>, y<sup><em>n</em></sup>.\n\n[^1]: The *first* note.\n[^2]: Two\n\n    paragraphs.\n\n1. Regular list",
+            markdown.trim()
+        );
+    }
+
+    #[test]
+    fn warning() {
+        let html = "<div class=\"docblock\"><p>Intro</p>
+<div class=\"warning\">
+<p>Be <strong>careful</strong> here.</p>
+<div class=\"example-wrap\"><pre class=\"rust rust-example-rendered\"><code>let x = 1;</code></pre></div></div>
+<p>After</p></div>";
+
+        let markdown = html_to_readme(html, Default::default()).unwrap();
+        assert_eq!(
+            "Intro\n\n> [!WARNING]\n> Be **careful** here.\n>\n> ```rust\n> let x = 1;\n> ```\n\nAfter",
+            markdown.trim()
+        );
+
+        let options = Options {
+            plain_warnings: true,
+            ..Default::default()
+        };
+        let markdown = html_to_readme(html, options).unwrap();
+        assert_eq!(
+            "Intro\n\n> **Warning**\n>\n> Be **careful** here.\n>\n> ```rust\n> let x = 1;\n> ```\n\nAfter",
+            markdown.trim()
+        );
+    }
+
+    #[test]
+    fn heading_links() {
+        let markdown = html_to_readme(
+            "<div class=\"docblock\"><p>See <a href=\"#no_std-examples\">below</a>, \
+<a href=\"#usage-1\">again</a> and <a href=\"#nowhere\">nothing</a>.</p>
+<h2 id=\"usage\"><a class=\"doc-anchor\" href=\"#usage\">§</a>Usage</h2>
+<h2 id=\"no_std-examples\"><a href=\"#no_std-examples\"><code>#![no_std]</code> Examples</a></h2>
+<h2 id=\"usage-1\"><a class=\"doc-anchor\" href=\"#usage-1\">§</a>Usage</h2>
+<h3 id=\"whats-new\">What’s <em>new</em>?</h3>
+<p><a href=\"#whats-new\">link</a></p></div>",
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "See [below](#no_std-examples), [again](#usage-1) and [nothing](#nowhere).
+
+## Usage
+
+## `#![no_std]` Examples
+
+## Usage
+
+### What’s *new*?
+
+[link](#whats-new)",
+            markdown.trim()
+        );
+
+        // Only links are rewritten, not code that looks like one
+        let markdown = html_to_readme(
+            "<div class=\"docblock\"><h2 id=\"intro\">Getting started</h2>
+<p><a href=\"#intro\">Start</a> with <code>[a](#intro)</code>.</p>
+<div class=\"example-wrap\"><pre class=\"language-md\"><code>[b](#intro)</code></pre></div></div>",
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            "## Getting started\n\n[Start](#getting-started) with `[a](#intro)`.\n\n```md\n[b](#intro)\n```",
+            markdown.trim()
+        );
+    }
+
+    #[test]
+    fn toc() {
+        let html = "<div class=\"docblock\"><h2 id=\"usage\"><a class=\"doc-anchor\" href=\"#usage\">§</a>Usage</h2>
+<h3 id=\"no_std-support\"><code>no_std</code> support</h3>
+<h2 id=\"usage-1\">Usage</h2></div>";
+        let convert = |flavor, format| {
+            let options = Options {
+                flavor,
+                format,
+                ..Default::default()
+            };
+            convert_page(html, options).unwrap()
+        };
+
+        let conversion = convert(Flavor::Gfm, Format::Markdown);
+        assert_eq!(
+            vec![
+                Heading {
+                    level: 2,
+                    text: "Usage".into(),
+                    anchor: Some("usage".into()),
+                },
+                Heading {
+                    level: 3,
+                    text: "no_std support".into(),
+                    anchor: Some("no_std-support".into()),
+                },
+                Heading {
+                    level: 2,
+                    text: "Usage".into(),
+                    anchor: Some("usage-1".into()),
+                },
+            ],
+            conversion.headings
+        );
+        assert_eq!(
+            "* [Usage](#usage)\n  * [no\\_std support](#no_std-support)\n* [Usage](#usage-1)",
+            conversion.toc
+        );
+        assert_eq!(
+            "* Usage\n  * no\\_std support\n* Usage",
+            convert(Flavor::CommonMark, Format::Markdown).toc
+        );
+        assert_eq!(
+            "- `Usage <usage_>`__\n\n  - `no_std support <no_std-support_>`__\n\n- `Usage <usage-1_>`__",
+            convert(Flavor::Gfm, Format::Rst).toc
+        );
+        assert_eq!(
+            "* <<usage,Usage>>\n** <<no_std-support,no&#95;std support>>\n* <<usage-1,Usage>>",
+            convert(Flavor::Gfm, Format::AsciiDoc).toc
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let html = "<div class=\"docblock\"><p>Intro</p>
+<h2 id=\"usage\">Usage</h2><p>Use it.</p>
+<div class=\"example-wrap\"><pre class=\"language-text\"><code># not a heading
+</code></pre></div>
+<h3 id=\"features\">Features</h3><p>Some.</p>
+<h2 id=\"todo\">Todo</h2><p>Everything.</p></div>";
+        let sections = |format| {
+            let options = Options {
+                format,
+                ..Default::default()
+            };
+            convert_page(html, options)
+                .unwrap()
+                .sections
+                .into_iter()
+                .map(|section| (section.heading.map(|heading| heading.text), section.text))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![
+                (None, "Intro".to_owned()),
+                (
+                    Some("Usage".to_owned()),
+                    "## Usage\n\nUse it.\n\n```text\n# not a heading\n```\n\n### Features\n\nSome."
+                        .to_owned()
+                ),
+                (
+                    Some("Features".to_owned()),
+                    "### Features\n\nSome.".to_owned()
+                ),
+                (Some("Todo".to_owned()), "## Todo\n\nEverything.".to_owned()),
+            ],
+            sections(Format::Markdown)
+        );
+        assert_eq!(
+            vec![
+                (None, "Intro".to_owned()),
+                (
+                    Some("Usage".to_owned()),
+                    ".. _usage:\n\nUsage\n=====\n\nUse it.\n\n.. code-block:: text\n\n    # not a heading\n\n.. _features:\n\nFeatures\n--------\n\nSome."
+                        .to_owned()
+                ),
+                (
+                    Some("Features".to_owned()),
+                    ".. _features:\n\nFeatures\n--------\n\nSome.".to_owned()
+                ),
+                (
+                    Some("Todo".to_owned()),
+                    ".. _todo:\n\nTodo\n====\n\nEverything.".to_owned()
+                ),
+            ],
+            sections(Format::Rst)
+        );
+
+        // Headings in warnings are quoted, which mustn't throw off the ones after them
+        let html = "<div class=\"docblock\"><h2 id=\"usage\">Usage</h2>
+<div class=\"warning\"><h3 id=\"careful\">Careful</h3><p>Really.</p></div>
+<h2 id=\"todo\">Todo</h2><p>Everything.</p></div>";
+        let conversion = convert_page(html, Default::default()).unwrap();
+        assert!(!conversion.readme.contains(SECTION_MARK));
+        assert_eq!(
+            vec![
+                (
+                    Some("Usage".to_owned()),
+                    "## Usage\n\n> [!WARNING]\n> ### Careful\n>\n> Really.".to_owned()
+                ),
+                (
+                    Some("Careful".to_owned()),
+                    "> ### Careful\n>\n> Really.".to_owned()
+                ),
+                (Some("Todo".to_owned()), "## Todo\n\nEverything.".to_owned()),
+            ],
+            conversion
+                .sections
+                .into_iter()
+                .map(|section| (section.heading.map(|heading| heading.text), section.text))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn exclude() {
+        let html = "<div class=\"docblock\"><p>Intro</p>
+<!-- doc2readme:skip-start -->
+<p>Only on docs.rs</p>
+<!-- doc2readme:skip-end -->
+<h2 id=\"usage\">Usage</h2><p>Use it.</p>
+<h2 id=\"todo\"><a class=\"doc-anchor\" href=\"#todo\">§</a>Todo</h2><p>Everything.</p>
+<h3 id=\"later\">Later</h3><p>More.</p>
+<h2 id=\"license\">License</h2><p>MIT</p></div>";
+        let options = Options {
+            exclude_sections: vec!["Todo".into()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "Intro\n\n## Usage\n\nUse it.\n\n## License\n\nMIT",
+            html_to_readme(html, options.clone()).unwrap().trim()
+        );
+        let options = Options {
+            format: Format::Text,
+            ..options
+        };
+        assert_eq!(
+            "Intro\n\nUsage\n=====\n\nUse it.\n\nLicense\n=======\n\nMIT",
+            html_to_readme(html, options).unwrap().trim()
+        );
+
+        // Headings can be nested, and are matched with either kind of punctuation
+        let html = "<div class=\"docblock\"><h2 id=\"usage\">Usage</h2>
+<div class=\"warning\"><p>Careful</p><h3 id=\"dont-panic\">Don’t panic</h3><p>Please.</p></div>
+<h2 id=\"license\">License</h2><p>MIT</p></div>";
+        let options = Options {
+            exclude_sections: vec!["Don't  panic".into()],
+            ascii_punctuation: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            "## Usage\n\n> [!WARNING]\n> Careful\n\n## License\n\nMIT",
+            html_to_readme(html, options).unwrap().trim()
+        );
+    }
+
+    #[test]
+    fn link_diagnostics() {
+        let html = "<div class='docblock'><p><a href='struct.Foo.html'>Foo</a> \
+<a href='https://doc.rust-lang.org/1.75.0/std/index.html'><code>std</code></a> \
+<a href='https://dagans.dev'>site</a> <a href='#usage'>usage</a> \
+<a href='#intro'>intro</a> <a href='#gone'>gone</a></p>
+<h2 id='usage'>Usage</h2><h2 id='intro'>Getting started</h2></div>";
+        let conversion = convert_page(
+            html,
+            Options {
+                unpin_std_docs: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let link = |text: &str, href: &str, change| LinkDiagnostic {
+            text: text.into(),
+            href: href.into(),
+            change,
+        };
+        assert_eq!(
+            vec![
+                link(
+                    "Foo",
+                    "struct.Foo.html",
+                    LinkChange::Dropped("relative link without base URL".into())
+                ),
+                link(
+                    "`std`",
+                    "https://doc.rust-lang.org/1.75.0/std/index.html",
+                    LinkChange::Rewritten("https://doc.rust-lang.org/std/index.html".into())
+                ),
+                link(
+                    "intro",
+                    "#intro",
+                    LinkChange::Rewritten("#getting-started".into())
+                ),
+                link("gone", "#gone", LinkChange::Unresolved),
+            ],
+            conversion.links
+        );
+
+        // reStructuredText links point at the targets written before headings
+        let conversion = convert_page(
+            html,
+            Options {
+                format: Format::Rst,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(conversion
+            .readme
+            .contains("`usage <usage_>`__ `intro <getting-started_>`__ gone"));
+        assert_eq!(
+            vec![
+                link(
+                    "intro",
+                    "#intro",
+                    LinkChange::Rewritten("#getting-started".into())
+                ),
+                link(
+                    "gone",
+                    "#gone",
+                    LinkChange::Dropped("no heading with this anchor".into())
+                ),
+            ],
+            conversion.links[1..]
+        );
+    }
+
+    #[test]
+    fn images() {
+        let html =
+            "<div class='docblock'><p><img src='assets/logo.png' alt='Logo' title='The \"logo\"'> \
+<img src='https://dagans.dev/a.png' alt='abs'> \
+<img src='diagram.svg' alt='A & B' width='200'></p></div>";
+
+        let markdown = html_to_readme(html, Default::default()).unwrap();
+        assert_eq!(
+            "![Logo](assets/logo.png \"The \\\"logo\\\"\") ![abs](https://dagans.dev/a.png) \
+<img src=\"diagram.svg\" alt=\"A &amp; B\" width=\"200\">",
+            markdown.trim()
+        );
+
+        let markdown = html_to_readme(
+            "<div class='docblock'><p><img src='a.png' alt='[x] \\ y]'></p></div>",
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!("![\\[x\\] \\\\ y\\]](a.png)", markdown.trim());
+
+        let markdown = html_to_readme(
+            "<div class='docblock'><p><img src='a.png' alt='snake_case and __init__'></p></div>",
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!("![snake_case and __init__](a.png)", markdown.trim());
+
+        let options = Options {
+            base_url: Some(String::from("https://docs.rs/foo/1.0.0/foo")),
+            ..Default::default()
+        };
+        let markdown = html_to_readme(html, options.clone()).unwrap();
+        assert_eq!(
+            "![Logo](https://docs.rs/foo/1.0.0/foo/assets/logo.png \"The \\\"logo\\\"\") \
+![abs](https://dagans.dev/a.png) \
+<img src=\"https://docs.rs/foo/1.0.0/foo/diagram.svg\" alt=\"A &amp; B\" width=\"200\">",
+            markdown.trim()
+        );
+
+        let options = Options {
+            asset_base_url: Some(String::from("https://raw.example.com/repo/main/")),
+            ..options
+        };
+        let markdown = html_to_readme(html, options.clone()).unwrap();
+        assert_eq!(
+            "![Logo](https://raw.example.com/repo/main/assets/logo.png \"The \\\"logo\\\"\") \
+![abs](https://dagans.dev/a.png) \
+<img src=\"https://raw.example.com/repo/main/diagram.svg\" alt=\"A &amp; B\" width=\"200\">",
+            markdown.trim()
+        );
+
+        // Images are reported like links, and dropped ones leave their alt text
+        let html = "<div class='docblock'><p><img src='logo.png' alt='Logo'> \
+<img src='//[oops' alt='Broken *logo*'></p></div>";
+        for format in [Format::Markdown, Format::Rst, Format::AsciiDoc] {
+            let options = Options {
+                format,
+                ..options.clone()
+            };
+            let conversion = convert_page(html, options).unwrap();
+            assert_eq!(
+                vec![
+                    LinkDiagnostic {
+                        text: "Logo".into(),
+                        href: "logo.png".into(),
+                        change: LinkChange::Rewritten(
+                            "https://raw.example.com/repo/main/logo.png".into()
+                        ),
+                    },
+                    LinkDiagnostic {
+                        text: "Broken *logo*".into(),
+                        href: "//[oops".into(),
+                        change: LinkChange::Dropped("invalid URL (invalid IPv6 address)".into()),
+                    },
+                ],
+                conversion.links,
+                "{format}"
+            );
+        }
+        let conversion = convert_page(html, options).unwrap();
+        assert_eq!(
+            "![Logo](https://raw.example.com/repo/main/logo.png) Broken \\*logo\\*",
+            conversion.readme.trim()
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        let markdown = "Some **strong**, *em* and snake_case text, [a\\_link](https://a.b/c_d) \\_kept\\_ `a_b`  \nbroken line  \n\n\n   \n- one\n  + two\n\n> quoted  \n>\n>\n> more\n\n```text\n- code  \n\n\n_kept_\n```";
+        assert_eq!(
+            "Some **strong**, *em* and snake_case text, [a\\_link](https://a.b/c_d) \\_kept\\_ `a_b`\\\nbroken line\n\n* one\n  * two\n\n> quoted\n>\n> more\n\n```text\n- code  \n\n\n_kept_\n```",
+            super::normalize(markdown)
+        );
+
+        let markdown = html_to_readme(
+            "<div class='docblock'><p><ins>Inserted</ins> and <u>underlined</u></p></div>",
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!("**Inserted** and **underlined**", markdown.trim());
+    }
+
+    #[test]
+    fn ascii_punctuation() {
+        let html = "<div class='docblock'><p>It’s “smart” – really — isn’t it… \
+<code>‘kept’ – “here”</code></p>
+<div class='example-wrap'><pre class='language-text'><code>“also” kept…</code></pre></div></div>";
+
+        let markdown = html_to_readme(html, Default::default()).unwrap();
+        assert_eq!(
+            "It’s “smart” – really — isn’t it… `‘kept’ – “here”`\n\n```text\n“also” kept…\n```",
+            markdown.trim()
+        );
+
+        let options = Options {
+            ascii_punctuation: true,
+            ..Default::default()
+        };
+        let markdown = html_to_readme(html, options).unwrap();
+        assert_eq!(
+            "It's \"smart\" -- really --- isn't it... `‘kept’ – “here”`\n\n```text\n“also” kept…\n```",
+            markdown.trim()
+        );
+    }
+
+    #[test]
+    fn flavors() {
+        let html = "<div class=\"docblock\"><p>See <a href=\"#usage\">usage</a>.\
+<sup id=\"fnref1\"><a href=\"#fn1\">1</a></sup></p>
+<h2 id=\"usage\">Usage</h2>
+<div class=\"warning\"><p>Careful</p></div>
+<table><thead><tr><th>A</th></tr></thead><tbody><tr><td><code>a &lt; b</code></td></tr></tbody></table>
+<div class=\"footnotes\"><hr><ol><li id=\"fn1\"><p>Note.&nbsp;<a href=\"#fnref1\">↩</a></p></li></ol></div></div>";
+        let convert = |flavor| {
+            let options = Options {
+                flavor,
+                ..Default::default()
+            };
+            html_to_readme(html, options).unwrap().trim().to_owned()
+        };
+
+        assert_eq!(
+            "See [usage](#usage).[^1]
+
+## Usage
+
+> [!WARNING]
+> Careful
+
+| A |
+| --- |
+| `a < b` |
+
+[^1]: Note.",
+            convert(Flavor::Gfm)
+        );
+        assert_eq!(
+            "See [usage](#usage).[^1]
+
+## Usage
+
+> **Warning**
+>
+> Careful
+
+| A |
+| --- |
+| `a < b` |
+
+[^1]: Note.",
+            convert(Flavor::CratesIo)
+        );
+        assert_eq!(
+            "See usage.<sup>1</sup>
+
+## Usage
+
+> **Warning**
+>
+> Careful
+
+<table><thead><tr><th>A</th></tr></thead><tbody><tr><td><code>a &lt; b</code></td></tr></tbody></table>
+
+<sup>1</sup> Note.",
+            convert(Flavor::CommonMark)
+        );
+    }
+
+    #[test]
+    fn formats() {
+        let html = r##"<div class="docblock"><p>Parse <em>fast</em> with <a href="struct.Parser.html"><code>Parser</code></a> and <strong>no</strong> <code>unsafe</code>.<sup id="fnref1"><a href="#fn1">1</a></sup></p>
+<p>C++ and C++, {name}, *word*, _word_ and #x#, see <a href="#usage">below</a>.</p>
+<h2 id="usage"><a class="doc-anchor" href="#usage">§</a>Usage</h2>
+<ul><li>One</li><li>Two<ul><li>Nested</li></ul></li></ul>
+<table><thead><tr><th>A</th><th>B</th></tr></thead><tbody><tr><td>1</td><td><code>x</code></td></tr></tbody></table>
+<div class="warning"><p>Careful</p></div>
+<div class="example-wrap"><pre class="rust rust-example-rendered"><code>let x = 1;</code></pre></div>
+<div class="footnotes"><hr><ol><li id="fn1"><p>A note.&nbsp;<a href="#fnref1">↩</a></p></li></ol></div></div>"##;
+        let convert = |format| {
+            let options = Options {
+                base_url: Some(String::from("https://docs.rs/foo/1.0.0/foo/")),
+                format,
+                ..Default::default()
+            };
+            html_to_readme(html, options).unwrap()
+        };
+
+        assert_eq!(
+            r#"Parse *fast* with `Parser <https://docs.rs/foo/1.0.0/foo/struct.Parser.html>`__ and **no** ``unsafe``.\ [1]_
+
+C++ and C++, {name}, \*word\*, _word\_ and #x#, see `below <usage_>`__.
+
+.. _usage:
+
+Usage
+=====
+
+- One
+
+- Two
+
+  - Nested
+
+.. list-table::
+   :header-rows: 1
+
+   * - A
+     - B
+   * - 1
+     - ``x``
+
+.. warning::
+
+   Careful
+
+.. code-block:: rust
+
+    let x = 1;
+
+.. [1] A note."#,
+            convert(Format::Rst)
+        );
+        assert_eq!(
+            r#"Parse _fast_ with link:https://docs.rs/foo/1.0.0/foo/struct.Parser.html[`+Parser+`] and *no* `+unsafe+`.footnote:[A note.]
+
+C{plus}{plus} and C{plus}{plus}, &#123;name}, {asterisk}word{asterisk}, &#95;word&#95; and &#35;x&#35;, see <<usage,below>>.
+
+[[usage]]
+== Usage
+
+* One
+* Two
+** Nested
+
+[options="header"]
+|===
+| A | B
+
+| 1 | `+x+`
+|===
+
+[WARNING]
+====
+Careful
+====
+
+[source,rust]
+----
+let x = 1;
+----"#,
+            convert(Format::AsciiDoc)
+        );
+        assert_eq!(
+            r#"Parse fast with Parser (https://docs.rs/foo/1.0.0/foo/struct.Parser.html) and no unsafe.[1]
+
+C++ and C++, {name}, *word*, _word_ and #x#, see below.
+
+Usage
+=====
+
+- One
+
+- Two
+
+  - Nested
+
+A | B
+1 | x
+
+Warning:
+
+    Careful
+
+    let x = 1;
+
+[1] A note."#,
+            convert(Format::Text)
+        );
+    }
+
     #[test]
     fn strip_relative_links() {
         let markdown = html_to_readme(
@@ -0,0 +1,86 @@
+// Removes documentation that only belongs on docs.rs before it's converted, so it's left out of
+// the README in every format.
+use crate::convert::replace_punctuation;
+use crate::markup::heading_text;
+use scraper::{ElementRef, Html, Node, Selector};
+
+const SKIP_START: &str = "doc2readme:skip-start";
+const SKIP_END: &str = "doc2readme:skip-end";
+
+fn is_marker(node: &Node, marker: &str) -> bool {
+    node.as_comment()
+        .is_some_and(|comment| comment.trim() == marker)
+}
+
+// Compare headings regardless of spacing and of whether rustdoc made the punctuation smart
+fn normalize_heading(text: &str) -> String {
+    replace_punctuation(&text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn heading_level(element: ElementRef) -> Option<usize> {
+    match element.value().name() {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+/// Remove everything between `<!-- doc2readme:skip-start -->` and `<!-- doc2readme:skip-end -->`
+/// markers, and the sections with the given headings, from the page's `.docblock`
+pub(crate) fn remove_excluded(html: &mut Html, exclude_sections: &[String]) {
+    let selector = Selector::parse(".docblock").expect("Valid selector");
+    let Some(docblock) = html.select(&selector).next() else {
+        return;
+    };
+
+    let mut excluded = Vec::new();
+    for node in docblock.descendants() {
+        if !is_marker(node.value(), SKIP_START) {
+            continue;
+        }
+        // Without an end marker, skip to the end of the enclosing element
+        excluded.push(node.id());
+        for sibling in node.next_siblings() {
+            excluded.push(sibling.id());
+            if is_marker(sibling.value(), SKIP_END) {
+                break;
+            }
+        }
+    }
+
+    // rustdoc doesn't nest sections, so a section is a heading and the siblings up to the next
+    // heading of the same or a higher level. Headings can be nested in other elements, e.g.
+    // warnings, in which case the section ends with that element
+    let exclude_sections = exclude_sections
+        .iter()
+        .map(|heading| normalize_heading(heading))
+        .collect::<Vec<_>>();
+    for heading in docblock.descendants().filter_map(ElementRef::wrap) {
+        let Some(level) = heading_level(heading) else {
+            continue;
+        };
+        if !exclude_sections.contains(&normalize_heading(&heading_text(heading))) {
+            continue;
+        }
+        excluded.push(heading.id());
+        for sibling in heading.next_siblings() {
+            if ElementRef::wrap(sibling)
+                .and_then(heading_level)
+                .is_some_and(|sibling_level| sibling_level <= level)
+            {
+                break;
+            }
+            excluded.push(sibling.id());
+        }
+    }
+
+    for id in excluded {
+        if let Some(mut node) = html.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+}
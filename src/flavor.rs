@@ -0,0 +1,126 @@
+//! Markdown dialects the README can be rendered for
+use anyhow::{bail, Error};
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+
+/// The Markdown dialect of the site the README is rendered on
+///
+/// Constructs a flavor doesn't support are degraded to something it renders correctly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum Flavor {
+    /// GitHub Flavored Markdown
+    #[default]
+    #[serde(rename = "gfm")]
+    Gfm,
+    /// Plain CommonMark, without any extensions
+    #[serde(rename = "commonmark")]
+    CommonMark,
+    /// GitLab Flavored Markdown
+    #[serde(rename = "gitlab")]
+    GitLab,
+    /// crates.io, which renders READMEs with the GFM extensions enabled
+    #[serde(rename = "crates-io")]
+    CratesIo,
+}
+
+impl Flavor {
+    /// Names accepted by [`Flavor::from_str`]
+    pub const NAMES: [&'static str; 4] = ["gfm", "commonmark", "gitlab", "crates-io"];
+
+    /// Does the flavor have `> [!WARNING]` style alerts?
+    pub fn alerts(self) -> bool {
+        self == Self::Gfm
+    }
+
+    /// Does the flavor have pipe tables?
+    pub fn tables(self) -> bool {
+        self != Self::CommonMark
+    }
+
+    /// Does the flavor have `[^label]` footnotes?
+    pub fn footnotes(self) -> bool {
+        self != Self::CommonMark
+    }
+
+    /// Does the flavor generate anchors for headings?
+    pub fn heading_anchors(self) -> bool {
+        self != Self::CommonMark
+    }
+
+    /// Compute the anchor the flavor generates for a heading, without disambiguation
+    pub fn slug(self, text: &str) -> String {
+        let slug: String = text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                '-' | '_' => Some(c),
+                c if c.is_alphanumeric() => Some(c),
+                _ => None,
+            })
+            .collect();
+
+        match self {
+            // GitLab also collapses runs of hyphens
+            Self::GitLab => slug
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-"),
+            // crates.io prefixes the ids with `user-content-`, but rewrites links to match
+            Self::Gfm | Self::CommonMark | Self::CratesIo => slug,
+        }
+    }
+}
+
+impl FromStr for Flavor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "gfm" => Self::Gfm,
+            "commonmark" => Self::CommonMark,
+            "gitlab" => Self::GitLab,
+            "crates-io" => Self::CratesIo,
+            _ => bail!(
+                "Unknown flavor '{s}'. Expected one of: {}",
+                Self::NAMES.join(", ")
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Flavor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Gfm => "gfm",
+            Self::CommonMark => "commonmark",
+            Self::GitLab => "gitlab",
+            Self::CratesIo => "crates-io",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for name in Flavor::NAMES {
+            assert_eq!(name, name.parse::<Flavor>().unwrap().to_string());
+        }
+        assert!("github".parse::<Flavor>().is_err());
+    }
+
+    #[test]
+    fn slugs() {
+        let heading = "`#![no_std]` -- Examples";
+        assert_eq!("no_std----examples", Flavor::Gfm.slug(heading));
+        assert_eq!("no_std-examples", Flavor::GitLab.slug(heading));
+        assert_eq!("no_std----examples", Flavor::CratesIo.slug(heading));
+    }
+}
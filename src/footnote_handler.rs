@@ -0,0 +1,222 @@
+use crate::flavor::Flavor;
+use crate::table_handler::write_html;
+use html2md::{
+    containers::ContainerHandler,
+    lists::{ListHandler, ListItemHandler},
+    paragraphs::ParagraphHandler,
+    StructuredPrinter, TagHandler, TagHandlerFactory,
+};
+use markup5ever_rcdom::{Handle, NodeData};
+use std::{cell::Cell, rc::Rc};
+
+// Converts rustdoc footnotes back to GFM footnotes.
+//
+// rustdoc renders a reference as `<sup id="fnref1"><a href="#fn1">1</a></sup>` and the
+// definitions as `<div class="footnotes"><hr><ol><li id="fn1">...</li></ol></div>`. Those tags
+// are also used for other things, so anything that isn't a footnote is passed on to html2md's
+// own handler for that tag.
+//
+// Flavors without footnotes get superscript labels, with each definition in its own paragraph.
+pub(crate) struct FootnoteHandler {
+    in_footnotes: Rc<Cell<bool>>,
+    flavor: Flavor,
+    tag: String,
+    start_pos: usize,
+    footnote: bool,
+    skip_descendants: bool,
+    fallback: Option<Box<dyn TagHandler>>,
+}
+
+fn attr(tag: &Handle, name: &str) -> Option<String> {
+    match tag.data {
+        NodeData::Element { ref attrs, .. } => attrs
+            .borrow()
+            .iter()
+            .find(|attr| attr.name.local.to_string() == name)
+            .map(|attr| attr.value.to_string()),
+        _ => None,
+    }
+}
+
+// Copies a tag and its descendants as HTML, which is what html2md does with `sup` by default
+#[derive(Default)]
+struct IdentityHandler;
+
+impl TagHandler for IdentityHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let mut html = String::new();
+        write_html(tag, &mut html, None);
+        printer.append_str(&html);
+    }
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+}
+
+// If `tag` is a footnote reference, get its label
+fn reference_label(tag: &Handle) -> Option<String> {
+    let children = tag.children.borrow();
+    let [anchor] = children.as_slice() else {
+        return None;
+    };
+    let label = attr(anchor, "href")?.strip_prefix("#fn")?.to_owned();
+    (!label.is_empty()).then_some(label)
+}
+
+impl FootnoteHandler {
+    fn fallback(&self) -> Box<dyn TagHandler> {
+        match self.tag.as_ref() {
+            "div" => Box::<ContainerHandler>::default(),
+            "ol" => Box::<ListHandler>::default(),
+            "li" => Box::<ListItemHandler>::default(),
+            "hr" => Box::<ParagraphHandler>::default(),
+            _ => Box::<IdentityHandler>::default(),
+        }
+    }
+}
+
+impl TagHandler for FootnoteHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        self.tag = match tag.data {
+            NodeData::Element { ref name, .. } => name.local.to_string(),
+            _ => String::new(),
+        };
+
+        let in_footnotes = self.in_footnotes.get();
+        match self.tag.as_ref() {
+            "sup" => {
+                if let Some(label) = reference_label(tag) {
+                    if self.flavor.footnotes() {
+                        printer.append_str(&format!("[^{label}]"));
+                    } else {
+                        printer.append_str(&format!("<sup>{label}</sup>"));
+                    }
+                    self.skip_descendants = true;
+                    return;
+                }
+            }
+            "div" => {
+                let class = attr(tag, "class").unwrap_or_default();
+                if class.split_whitespace().any(|class| class == "footnotes") {
+                    self.footnote = true;
+                    self.in_footnotes.set(true);
+                    printer.insert_newline();
+                    printer.insert_newline();
+                    return;
+                }
+            }
+            "ol" | "hr" if in_footnotes => {
+                self.footnote = true;
+                return;
+            }
+            "li" if in_footnotes => {
+                if let Some(label) = attr(tag, "id").and_then(|id| {
+                    id.strip_prefix("fn")
+                        .filter(|label| !label.is_empty())
+                        .map(String::from)
+                }) {
+                    self.footnote = true;
+                    if !printer.data.ends_with('\n') {
+                        printer.insert_newline();
+                    }
+                    if self.flavor.footnotes() {
+                        printer.append_str(&format!("[^{label}]: "));
+                    } else {
+                        printer.insert_newline();
+                        printer.append_str(&format!("<sup>{label}</sup> "));
+                    }
+                    self.start_pos = printer.data.len();
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        let mut fallback = self.fallback();
+        fallback.handle(tag, printer);
+        self.fallback = Some(fallback);
+    }
+
+    fn skip_descendants(&self) -> bool {
+        self.skip_descendants
+            || self
+                .fallback
+                .as_ref()
+                .is_some_and(|fallback| fallback.skip_descendants())
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if let Some(fallback) = &mut self.fallback {
+            fallback.after_handle(printer);
+            return;
+        }
+
+        if !self.footnote {
+            return;
+        }
+
+        match self.tag.as_ref() {
+            "div" => {
+                self.in_footnotes.set(false);
+                printer.insert_newline();
+                printer.insert_newline();
+            }
+            "li" if !self.flavor.footnotes() => {
+                let definition = printer.data.split_off(self.start_pos);
+                printer.append_str(definition.trim());
+                printer.insert_newline();
+            }
+            "li" => {
+                // Paragraphs after the first must be indented to stay part of the definition
+                let definition = printer.data.split_off(self.start_pos);
+                let definition = definition
+                    .trim()
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            String::new()
+                        } else {
+                            format!("    {line}")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                printer.append_str(definition.trim_start());
+                printer.insert_newline();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FootnoteHandlerFactory {
+    in_footnotes: Rc<Cell<bool>>,
+    flavor: Flavor,
+}
+
+impl FootnoteHandlerFactory {
+    pub fn new(flavor: Flavor) -> Self {
+        Self {
+            flavor,
+            ..Default::default()
+        }
+    }
+}
+
+impl TagHandlerFactory for FootnoteHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(FootnoteHandler {
+            in_footnotes: self.in_footnotes.clone(),
+            flavor: self.flavor,
+            tag: Default::default(),
+            start_pos: Default::default(),
+            footnote: false,
+            skip_descendants: false,
+            fallback: None,
+        })
+    }
+}
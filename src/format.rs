@@ -0,0 +1,65 @@
+//! Markup languages the README can be written in
+use anyhow::{bail, Error};
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+
+/// The markup language of the generated README
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum Format {
+    /// Markdown, in the dialect selected by [`Flavor`](crate::flavor::Flavor)
+    #[default]
+    #[serde(rename = "markdown")]
+    Markdown,
+    /// reStructuredText, as used by PyPI and Sphinx
+    #[serde(rename = "rst")]
+    Rst,
+    /// AsciiDoc
+    #[serde(rename = "asciidoc")]
+    AsciiDoc,
+    /// Plain text
+    #[serde(rename = "text")]
+    Text,
+}
+
+impl Format {
+    /// Names accepted by [`Format::from_str`]
+    pub const NAMES: [&'static str; 4] = ["markdown", "rst", "asciidoc", "text"];
+
+    /// The usual file extension for the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Rst => "rst",
+            Self::AsciiDoc => "adoc",
+            Self::Text => "txt",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "markdown" => Self::Markdown,
+            "rst" => Self::Rst,
+            "asciidoc" => Self::AsciiDoc,
+            "text" => Self::Text,
+            _ => bail!(
+                "Unknown format '{s}'. Expected one of: {}",
+                Self::NAMES.join(", ")
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Markdown => "markdown",
+            Self::Rst => "rst",
+            Self::AsciiDoc => "asciidoc",
+            Self::Text => "text",
+        })
+    }
+}
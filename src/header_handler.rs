@@ -1,31 +1,130 @@
+use crate::convert::{replace_punctuation, Heading, SECTION_MARK};
+use crate::flavor::Flavor;
 use html2md::{StructuredPrinter, TagHandler, TagHandlerFactory};
 use markup5ever_rcdom::{Handle, NodeData};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// The headings emitted during conversion, with the anchors the target flavor generates for them
+#[derive(Debug, Default)]
+pub(crate) struct Headings {
+    flavor: Flavor,
+    pub list: Vec<Heading>,
+    // Each heading's `id` in the rustdoc HTML, in the same order as `list`
+    ids: Vec<Option<String>>,
+    // How many times each slug has been used, for disambiguating duplicate headings
+    slug_counts: HashMap<String, usize>,
+}
+
+impl Headings {
+    pub fn new(flavor: Flavor) -> Self {
+        Self {
+            flavor,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, level: usize, text: &str, id: Option<String>) -> &mut Heading {
+        let anchor = self.flavor.heading_anchors().then(|| {
+            let slug = self.flavor.slug(text);
+            let count = self.slug_counts.entry(slug.clone()).or_default();
+            let anchor = if *count == 0 {
+                slug
+            } else {
+                format!("{slug}-{count}")
+            };
+            *count += 1;
+            anchor
+        });
+
+        self.ids.push(id);
+        self.list.push(Heading {
+            level,
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+            anchor,
+        });
+        self.list.last_mut().expect("Heading was just pushed")
+    }
+
+    /// Find the heading a `#fragment` link in the rustdoc HTML points to
+    pub fn find(&self, fragment: &str) -> Option<&Heading> {
+        self.ids
+            .iter()
+            .position(|id| id.as_deref() == Some(fragment))
+            .map(|i| &self.list[i])
+            .or_else(|| {
+                self.list
+                    .iter()
+                    .find(|heading| heading.anchor.as_deref() == Some(fragment))
+            })
+    }
+}
+
+// Collect the text of a heading, minus rustdoc's `§` anchor
+fn text_content(node: &Handle, text: &mut String) {
+    match node.data {
+        NodeData::Text { ref contents } => text.push_str(&contents.borrow()),
+        NodeData::Element { ref attrs, .. } => {
+            let is_doc_anchor = attrs.borrow().iter().any(|attr| {
+                attr.name.local.to_string() == "class" && attr.value == "doc-anchor".into()
+            });
+            if !is_doc_anchor {
+                for child in node.children.borrow().iter() {
+                    text_content(child, text);
+                }
+            }
+        }
+        _ => {}
+    }
+}
 
-#[derive(Default)]
 pub struct HeaderHandler {
     header_type: String,
+    headings: Rc<RefCell<Headings>>,
+    ascii_punctuation: bool,
 }
 
 impl TagHandler for HeaderHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
-        self.header_type = match tag.data {
-            NodeData::Element { ref name, .. } => name.local.to_string(),
-            _ => String::new(),
+        let (header_type, id) = match tag.data {
+            NodeData::Element {
+                ref name,
+                ref attrs,
+                ..
+            } => (
+                name.local.to_string(),
+                attrs
+                    .borrow()
+                    .iter()
+                    .find(|attr| attr.name.local.to_string() == "id")
+                    .map(|attr| attr.value.to_string()),
+            ),
+            _ => (String::new(), None),
         };
+        self.header_type = header_type;
 
         printer.insert_newline();
         printer.insert_newline();
-        match self.header_type.as_ref() {
-            "h1" => printer.append_str("# "),
-            "h2" => printer.append_str("## "),
-            "h3" => printer.append_str("### "),
-            "h4" => printer.append_str("#### "),
-            "h5" => printer.append_str("##### "),
-            "h6" => printer.append_str("###### "),
+        let level = match self.header_type.as_ref() {
+            "h1" => 1,
+            "h2" => 2,
+            "h3" => 3,
+            "h4" => 4,
+            "h5" => 5,
+            "h6" => 6,
             _ => {
                 panic!("This is not a header")
             }
+        };
+        printer.append_str(&SECTION_MARK.to_string());
+        printer.append_str(&"#".repeat(level));
+        printer.append_str(" ");
+
+        let mut text = String::new();
+        text_content(tag, &mut text);
+        if self.ascii_punctuation {
+            text = replace_punctuation(&text);
         }
+        self.headings.borrow_mut().push(level, &text, id);
     }
 
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
@@ -34,13 +133,27 @@ impl TagHandler for HeaderHandler {
     }
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct HeaderHandlerFactory;
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HeaderHandlerFactory {
+    pub headings: Rc<RefCell<Headings>>,
+    ascii_punctuation: bool,
+}
+
+impl HeaderHandlerFactory {
+    pub fn new(flavor: Flavor, ascii_punctuation: bool) -> Self {
+        Self {
+            headings: Rc::new(RefCell::new(Headings::new(flavor))),
+            ascii_punctuation,
+        }
+    }
+}
 
 impl TagHandlerFactory for HeaderHandlerFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
         Box::new(HeaderHandler {
             header_type: Default::default(),
+            headings: self.headings.clone(),
+            ascii_punctuation: self.ascii_punctuation,
         })
     }
 }
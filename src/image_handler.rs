@@ -0,0 +1,132 @@
+use crate::convert::{LinkChange, LinkDiagnostic, MARKDOWN_ESCAPE_REGEX};
+use html2md::{StructuredPrinter, TagHandler, TagHandlerFactory};
+use markup5ever_rcdom::{Handle, NodeData};
+use std::{cell::RefCell, rc::Rc};
+use url::Url;
+
+// Attributes kept when an image has to be written as inline HTML, in output order
+const HTML_ATTRS: [&str; 6] = ["src", "alt", "title", "width", "height", "align"];
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+// Escape what would end the `[...]` of a Markdown image early
+fn escape_alt(alt: &str) -> String {
+    alt.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+/// Resolve a relative image `src` against the base URL
+///
+/// Absolute URLs (including `data:` URLs) are kept, as are relative ones when there's nothing to
+/// resolve them against. Like [`resolve_link`](crate::anchor_handler::resolve_link), returns the
+/// URL, unless the image has to be dropped, and what changed about it.
+pub(crate) fn resolve_src(
+    src: &str,
+    base_url: Option<&Url>,
+) -> (Option<String>, Option<LinkChange>) {
+    if Url::parse(src).is_ok() {
+        return (Some(src.to_owned()), None);
+    }
+    match base_url {
+        Some(base_url) => match base_url.join(src) {
+            Ok(url) => (
+                Some(url.to_string()),
+                Some(LinkChange::Rewritten(url.to_string())),
+            ),
+            Err(err) => (
+                None,
+                Some(LinkChange::Dropped(format!("invalid URL ({err})"))),
+            ),
+        },
+        None => (Some(src.to_owned()), None),
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ImageHandler {
+    base_url: Option<Url>,
+    links: Rc<RefCell<Vec<LinkDiagnostic>>>,
+}
+
+impl TagHandler for ImageHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let NodeData::Element { ref attrs, .. } = tag.data else {
+            return;
+        };
+        let attrs = attrs.borrow();
+        let attr = |name: &str| {
+            attrs
+                .iter()
+                .find(|attr| attr.name.local.to_string() == name)
+                .map(|attr| attr.value.to_string())
+        };
+
+        let href = attr("src").unwrap_or_default();
+        let alt = attr("alt").unwrap_or_default();
+        let (src, change) = resolve_src(&href, self.base_url.as_ref());
+        if let Some(change) = change {
+            self.links.borrow_mut().push(LinkDiagnostic {
+                text: alt.clone(),
+                href,
+                change,
+            });
+        }
+        // Dropped images leave their alt text behind, like dropped links leave their text
+        let Some(src) = src else {
+            printer.append_str(&MARKDOWN_ESCAPE_REGEX.replace_all(&alt, "\\$0"));
+            return;
+        };
+
+        // Markdown images can't be sized, so fall back to HTML
+        if ["width", "height", "align"]
+            .into_iter()
+            .any(|name| attr(name).is_some())
+        {
+            printer.append_str("<img");
+            for name in HTML_ATTRS {
+                let value = if name == "src" {
+                    Some(src.clone())
+                } else {
+                    attr(name)
+                };
+                if let Some(value) = value {
+                    printer.append_str(&format!(" {name}=\"{}\"", escape_html(&value)));
+                }
+            }
+            printer.append_str(">");
+            return;
+        }
+
+        let title = attr("title")
+            .map(|title| format!(" \"{}\"", title.replace('"', "\\\"")))
+            .unwrap_or_default();
+        printer.append_str(&format!(
+            "![{}]({}{title})",
+            escape_alt(&alt),
+            src.replace(' ', "%20"),
+        ));
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ImageHandlerFactory {
+    pub base_url: Option<Url>,
+    pub links: Rc<RefCell<Vec<LinkDiagnostic>>>,
+}
+
+impl TagHandlerFactory for ImageHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(ImageHandler {
+            base_url: self.base_url.clone(),
+            links: self.links.clone(),
+        })
+    }
+}
@@ -0,0 +1,139 @@
+//! Locate the rustdoc page of an item within a crate
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+// Prefixes rustdoc uses for item pages, e.g. `struct.Foo.html`
+const ITEM_KINDS: [&str; 15] = [
+    "struct",
+    "enum",
+    "trait",
+    "union",
+    "type",
+    "fn",
+    "macro",
+    "derive",
+    "attr",
+    "constant",
+    "static",
+    "traitalias",
+    "primitive",
+    "keyword",
+    "foreigntype",
+];
+
+/// A rustdoc page within a crate's documentation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemPage {
+    /// Path to the page's HTML file
+    pub path: PathBuf,
+    /// Directory of the page relative to the crate's documentation root, with a trailing slash
+    /// (empty for the crate root)
+    ///
+    /// Relative links on the page are relative to this directory.
+    pub url_dir: String,
+}
+
+/// Find the page for `item` (e.g. `module::Type`) in the crate documented at `crate_doc_dir`
+///
+/// Modules resolve to their `index.html`, other items to their `<kind>.<name>.html` page. A
+/// leading `crate::` is ignored.
+pub fn find_page(crate_doc_dir: &Path, item: &str) -> Result<ItemPage> {
+    let mut segments: Vec<_> = item
+        .trim_start_matches("crate::")
+        .split("::")
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    let Some(name) = segments.pop() else {
+        return Ok(ItemPage {
+            path: crate_doc_dir.join("index.html"),
+            url_dir: String::new(),
+        });
+    };
+
+    let parent_url_dir: String = segments
+        .iter()
+        .map(|segment| format!("{segment}/"))
+        .collect();
+    let parent_dir = crate_doc_dir.join(&parent_url_dir);
+
+    let module_page = parent_dir.join(name).join("index.html");
+    if module_page.is_file() {
+        return Ok(ItemPage {
+            path: module_page,
+            url_dir: format!("{parent_url_dir}{name}/"),
+        });
+    }
+
+    for kind in ITEM_KINDS {
+        let item_page = parent_dir.join(format!("{kind}.{name}.html"));
+        if item_page.is_file() {
+            return Ok(ItemPage {
+                path: item_page,
+                url_dir: parent_url_dir,
+            });
+        }
+    }
+
+    bail!("Could not find documentation for `{item}`");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn find_items() {
+        let doc_dir = TempDir::new().unwrap();
+        let doc_dir = doc_dir.path();
+        fs::create_dir_all(doc_dir.join("guide/advanced")).unwrap();
+        for file in [
+            "index.html",
+            "trait.Frobnicate.html",
+            "guide/index.html",
+            "guide/struct.Config.html",
+            "guide/advanced/index.html",
+        ] {
+            fs::write(doc_dir.join(file), "").unwrap();
+        }
+
+        assert_eq!(
+            find_page(doc_dir, "").unwrap(),
+            ItemPage {
+                path: doc_dir.join("index.html"),
+                url_dir: "".into()
+            }
+        );
+        assert_eq!(
+            find_page(doc_dir, "Frobnicate").unwrap(),
+            ItemPage {
+                path: doc_dir.join("trait.Frobnicate.html"),
+                url_dir: "".into()
+            }
+        );
+        assert_eq!(
+            find_page(doc_dir, "crate::guide").unwrap(),
+            ItemPage {
+                path: doc_dir.join("guide/index.html"),
+                url_dir: "guide/".into()
+            }
+        );
+        assert_eq!(
+            find_page(doc_dir, "guide::Config").unwrap(),
+            ItemPage {
+                path: doc_dir.join("guide/struct.Config.html"),
+                url_dir: "guide/".into()
+            }
+        );
+        assert_eq!(
+            find_page(doc_dir, "guide::advanced").unwrap(),
+            ItemPage {
+                path: doc_dir.join("guide/advanced/index.html"),
+                url_dir: "guide/advanced/".into()
+            }
+        );
+        assert!(find_page(doc_dir, "guide::Missing").is_err());
+    }
+}
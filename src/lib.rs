@@ -0,0 +1,214 @@
+//! Convert crate documention into a README
+//!
+//! Alternative to [cargo-readme](https://docs.rs/cargo-readme). Unlike `cargo-readme`,
+//! `cargo-doc2readme` parses the output of rustdoc instead of extracting the doc comments directly
+//! from the rust source. The main advantage here is that `cargo-doc2readme` can handle relative
+//! links in crate documentation.
+//!
+//! # Basic Usage
+//!
+//! Install:
+//!
+//! ```shell
+//! cargo install cargo-doc2readme --git https://github.com/Property404/doc2readme
+//! ```
+//!
+//! Usage:
+//!
+//! ```shell
+//! $ cargo doc2readme -o README.md
+//! ```
+//!
+//! Documentation is built with `cargo doc` first, and the command fails if that does. Use
+//! `--no-build` to skip building when the docs are already up to date.
+//!
+//! In CI, use `--check` to verify the README is up to date without modifying it. If the
+//! generated README differs from the output file, a diff is printed and the command fails:
+//!
+//! ```shell
+//! $ cargo doc2readme --check -o README.md
+//! ```
+//!
+//! Relative links (such as intra-doc links) are dropped unless a base URL is provided with
+//! `--base-url`. Use `--docs-rs` to link to the docs.rs page for the crate's current version
+//! instead:
+//!
+//! ```shell
+//! $ cargo doc2readme --docs-rs -o README.md
+//! ```
+//!
+//! Relative image sources are resolved against the base URL as well. Use `--asset-base-url` to
+//! resolve them against a different URL instead, such as the repository's raw file URL.
+//!
+//! rustdoc renders quotes, dashes and ellipses as their typographic counterparts. Use
+//! `--ascii-punctuation` to turn them back into ASCII, leaving code untouched.
+//!
+//! The output targets GitHub Flavored Markdown by default. Use `--flavor` to target `commonmark`,
+//! `gitlab` or `crates-io` instead. Alerts, tables, footnotes and heading links are degraded to
+//! something the flavor can render when it doesn't support them.
+//!
+//! Use `--format rst`, `--format asciidoc` or `--format text` to write reStructuredText, AsciiDoc
+//! or plain text instead of Markdown. Each format has its own default template, and in workspace
+//! mode the output defaults to `README.rst`, `README.adoc` or `README.txt`.
+//!
+//! Links that are dropped, or point to a missing heading, are listed on stderr, as are rewritten
+//! ones with `--verbose`. Use `--deny-dropped-links` to fail instead of writing a README that's
+//! missing links. Images are reported the same way.
+//!
+//! Documentation that only belongs on docs.rs can be left out of the README. Use
+//! `--exclude-section <heading>` to leave out a section and its subsections, as many times as
+//! needed. Headings are matched by their text, with plain quotes and dashes matching rustdoc's
+//! smart ones. Anything between skip markers is left out too:
+//!
+//! ```markdown
+//! <!-- doc2readme:skip-start -->
+//! This paragraph is only on docs.rs.
+//! <!-- doc2readme:skip-end -->
+//! ```
+//!
+//! Documentation is built with `--cfg doc2readme`, on top of any configured `RUSTDOCFLAGS` or
+//! `build.rustdocflags`, so text can also be added to the README only. It's built in a
+//! `doc2readme` directory inside the target directory, leaving the regular documentation alone:
+//!
+//! ```rust,ignore
+//! #![cfg_attr(doc2readme, doc = "Install with `cargo install my-crate`.")]
+//! ```
+//!
+//! Declare the `cfg` to keep `rustc` from warning about it:
+//!
+//! ```toml
+//! [lints.rust]
+//! unexpected_cfgs = { level = "warn", check-cfg = ["cfg(doc2readme)"] }
+//! ```
+//!
+//! The README is generated from the crate root's documentation by default. Use `--item` to use
+//! the documentation of a module or other item instead:
+//!
+//! ```shell
+//! $ cargo doc2readme --item guide -o README.md
+//! ```
+//!
+//! When a package has both a library and binaries, the library's documentation is used. Use
+//! `--lib` or `--bin <name>` to choose a target explicitly.
+//!
+//! In a workspace, `--workspace` generates a README for every member crate in one run. Each
+//! crate's template and output file are looked up relative to that crate's directory, and the output
+//! defaults to `README.md`:
+//!
+//! ```shell
+//! $ cargo doc2readme --workspace
+//! ```
+//!
+//! # Configuration
+//!
+//! Options can be set in `Cargo.toml` instead of being repeated on every invocation. Options in
+//! `[package.metadata.doc2readme]` take precedence over `[workspace.metadata.doc2readme]`, and
+//! command line options take precedence over both. Paths are relative to the package directory.
+//!
+//! Options that are turned on in `Cargo.toml` can be turned off on the command line with their
+//! `--no-` form, e.g. `--no-unpin-std-docs`. With `--workspace`, `rustdoc-args` are only read from
+//! `[workspace.metadata.doc2readme]`, since all crates are documented at once.
+//!
+//! ```toml
+//! [package.metadata.doc2readme]
+//! docs-rs = true
+//! template = "README.tpl"
+//! output = "README.md"
+//! unpin-std-docs = true
+//! exclude-sections = ["Todo"]
+//! rustdoc-args = ["--all-features"]
+//! ```
+//!
+//! # Templating
+//!
+//! `cargo-doc2readme` uses [minjinja](https://docs.rs/minijinja) as its
+//! templating engine, which happens to be a superset of `cargo-readme`'s templating engine. Like
+//! `cargo-readme`, `cargo-doc2readme` uses `README.tpl` as the template by default if it exists,
+//! but this can be overridden with the `--template` command line option.
+//!
+//! The default template is:
+//!
+//! ```jinja
+#![doc = include_str!("./DEFAULT_TEMPLATE.tpl")]
+//! ```
+//!
+//! ## Template variables
+//!
+//! * `crate` - the crate name, alias for `package.name`
+//! * `license` - the crate license, alias for `package.license`
+//! * `readme` - the generated readme text
+//! * `toc` - a table of contents, as a nested list linking to each heading of the generated
+//!   readme text
+//! * `headings` - the headings of the generated readme text, each with a `level` (1 to 6), its
+//!   `text` and the `anchor` it can be linked to with, if the output format has any
+//! * `sections` - the generated readme text of each section, by heading text, e.g.
+//!   `{{ sections["Example"] }}`. A section includes its heading and subsections
+//! * `section_list` - the top level sections, in order, each with its `heading` and `text`. The
+//!   text before the first heading comes first, with no heading
+//! * `version` - the crate version, alias for `package.version`
+//! * `description` - the crate description
+//! * `repository` - the crate repository URL
+//! * `homepage` - the crate homepage URL
+//! * `documentation` - the crate documentation URL
+//! * `authors` - the crate authors, as a list
+//! * `keywords` - the crate keywords, as a list
+//! * `categories` - the crate categories, as a list
+//! * `rust_version` - the minimum supported Rust version
+//! * `edition` - the Rust edition, e.g. `2021`
+//! * `features` - the crate features, each mapped to the features and dependencies it enables
+//! * `dependencies` - the crate dependencies, each with its `name`, the `package` it refers to,
+//!   its `version` requirement, whether it's `optional` and the `features` it enables
+//! * `package` - All package keys
+//!
+//! Values inherited from the workspace are resolved.
+//!
+//! For example, to leave out the "Todo" section:
+//!
+//! ```jinja
+//! {% for section in section_list if section.heading.text != "Todo" %}
+//! {{ section.text }}
+//! {% endfor %}
+//! ```
+//!
+//! # Library
+//!
+//! The conversion pipeline is also available as a library, for use in build scripts, tests or
+//! other tooling:
+//!
+//! ```rust,no_run
+//! use cargo_doc2readme::{build::Docs, convert, manifest::ProjectInfo, template};
+//! use std::fs;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let project_info = ProjectInfo::new()?;
+//! let krate = project_info.select_crate(None)?;
+//! let target_name = krate.target_name(&Default::default())?;
+//! Docs::build(&[], &project_info.doc_target_dir())?;
+//! let html = fs::read_to_string(project_info.doc_path(target_name))?;
+//! let conversion = convert::convert_page(&html, Default::default())?;
+//! let readme = template::render(template::DEFAULT_TEMPLATE, &krate.name, &krate.manifest, &conversion)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Todo
+//!
+//! * Get dependencies published
+mod anchor_handler;
+pub mod build;
+mod code_handler;
+pub mod config;
+pub mod convert;
+mod exclude;
+pub mod flavor;
+mod footnote_handler;
+pub mod format;
+mod header_handler;
+mod image_handler;
+pub mod item;
+pub mod manifest;
+mod markup;
+mod table_handler;
+pub mod template;
+mod underline_handler;
+mod warning_handler;
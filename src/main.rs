@@ -1,66 +1,23 @@
-//! Convert crate documention into a README
+//! Command line interface for `cargo-doc2readme`
 //!
-//! Alternative to [cargo-readme](https://docs.rs/cargo-readme). Unlike `cargo-readme`,
-//! `cargo-doc2readme` parses the output of rustdoc instead of extracting the doc comments directly
-//! from the rust source. The main advantage here is that `cargo-doc2readme` can handle relative
-//! links in crate documentation.
-//!
-//! # Basic Usage
-//!
-//! Install:
-//!
-//! ```shell
-//! cargo install cargo-doc2readme --git https://github.com/Property404/doc2readme
-//! ```
-//!
-//! Usage:
-//!
-//! ```shell
-//! $ cargo doc2readme -o README.md
-//! ```
-//!
-//! # Templating
-//!
-//! `cargo-doc2readme` uses [minjinja](https://docs.rs/minijinja) as its
-//! templating engine, which happens to be a superset of `cargo-readme`'s templating engine. Like
-//! `cargo-readme`, `cargo-doc2readme` uses `README.tpl` as the template by default if it exists,
-//! but this can be overridden with the `--template` command line option.
-//!
-//! The default template is:
-//!
-//! ```jinja
-#![doc = include_str!("./DEFAULT_TEMPLATE.tpl")]
-//! ```
-//!
-//! ## Template variables
-//!
-//! * `crate` - the crate name, alias for `package.name`
-//! * `license` - the crate license, alias for `package.license`
-//! * `readme` - the generated readme text
-//! * `version` - the crate version, alias for `package.version`
-//! * `package` - All package keys
-//!
-//! # Todo
-//!
-//! * Get dependencies published
-mod anchor_handler;
-mod code_handler;
-mod convert;
-mod header_handler;
-mod manifest;
-
-use anyhow::{anyhow, bail, Result};
-use convert::Options;
-use manifest::ProjectInfo;
-use minijinja::{context, Environment};
+//! See the library documentation for details.
+use anyhow::{anyhow, bail, Context, Result};
+use cargo_doc2readme::{
+    build::Docs,
+    config::Config,
+    convert::{self, LinkChange, Options},
+    item::{self, ItemPage},
+    manifest::{Crate, ProjectInfo},
+    template,
+};
 use schmargs::{ArgsWithHelp, Schmargs};
+use similar::TextDiff;
 use std::{
     env,
     fs::{self, File},
-    io::Write,
-    path::Path,
-    process::{self, Command},
-    str,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process, str,
 };
 
 const DEFAULT_TEMPLATE_PATH: &str = "README.tpl";
@@ -72,27 +29,97 @@ struct BareArgs {
     /// Unpin the version for `std`/`core`/`alloc` docs links
     #[arg(long)]
     unpin_std_docs: bool,
+    /// Keep the version for `std`/`core`/`alloc` docs links
+    #[arg(long)]
+    no_unpin_std_docs: bool,
+    /// Render warning blocks as plain blockquotes instead of GitHub alerts
+    #[arg(long)]
+    plain_warnings: bool,
+    /// Render warning blocks as the flavor allows
+    #[arg(long)]
+    no_plain_warnings: bool,
+    /// Replace typographic quotes, dashes and ellipses with ASCII
+    #[arg(long)]
+    ascii_punctuation: bool,
+    /// Keep typographic quotes, dashes and ellipses
+    #[arg(long)]
+    no_ascii_punctuation: bool,
+    /// Markdown flavor to target: gfm, commonmark, gitlab or crates-io
+    #[arg(long)]
+    flavor: Option<String>,
+    /// Output format: markdown, rst, asciidoc or text
+    #[arg(long)]
+    format: Option<String>,
+    /// Fail if any links are dropped during conversion
+    #[arg(long)]
+    deny_dropped_links: bool,
+    /// Don't fail if links are dropped during conversion
+    #[arg(long)]
+    no_deny_dropped_links: bool,
+    /// Leave out the section with this heading
+    #[arg(long, default_value)]
+    exclude_section: Vec<String>,
     /// Don't use any templating
     #[arg(long)]
     no_template: bool,
+    /// Don't run `cargo doc`; use the docs from the last run in `target/doc2readme`
+    #[arg(long)]
+    no_build: bool,
     /// Arguments to pass to `cargo doc`
     #[arg(long, default_value)]
     rustdoc_args: Vec<String>,
     /// Base URL for relative links
     #[arg(short = 'u', long)]
     base_url: Option<String>,
+    /// Base URL for relative image sources (defaults to the base URL)
+    #[arg(long)]
+    asset_base_url: Option<String>,
+    /// Use the crate's docs.rs page as the base URL for relative links
+    #[arg(long)]
+    docs_rs: bool,
+    /// Don't use the crate's docs.rs page as the base URL
+    #[arg(long)]
+    no_docs_rs: bool,
     /// The template to use, if any
     #[arg(short, long)]
     template: Option<String>,
     /// Output path
     #[arg(short, long)]
     output: Option<String>,
-    /// The crate from which to extract docs
+    /// Document the library target
+    #[arg(long)]
+    lib: bool,
+    /// Document the binary target with this name
+    #[arg(long)]
+    bin: Option<String>,
+    /// Use the docs of an item (e.g. `module::Type`) instead of the crate root
+    #[arg(long)]
+    item: Option<String>,
+    /// Check that the output file is up to date instead of writing to it
+    #[arg(long)]
+    check: bool,
+    /// Also list links that were rewritten, not just dropped or unresolved ones
+    #[arg(short, long)]
+    verbose: bool,
+    /// Generate a README for every crate in the workspace
+    #[arg(long)]
+    workspace: bool,
+    /// The crate from which to extract docs, by default the one in the current directory
     #[arg(value_name = "CRATE")]
     crate_name: Option<String>,
 }
 type Args = ArgsWithHelp<BareArgs>;
 
+/// A boolean option set with `--<name>` or `--no-<name>`, if either was given
+fn flag(name: &str, on: bool, off: bool) -> Result<Option<bool>> {
+    match (on, off) {
+        (true, true) => bail!("Cannot specify both `--{name}` and `--no-{name}`"),
+        (true, false) => Ok(Some(true)),
+        (false, true) => Ok(Some(false)),
+        (false, false) => Ok(None),
+    }
+}
+
 fn main() -> Result<()> {
     // We have to skip twice because `cargo doc2readme` invokes as `cargo-doc2readme doc2readme`
     //
@@ -118,87 +145,244 @@ fn main() -> Result<()> {
         }
     };
 
-    // Run `cargo doc` so docs and `target` directory is created
-    Command::new("cargo")
-        .arg("doc")
-        .arg("--no-deps")
-        .args(args.rustdoc_args.into_iter())
-        .status()?;
+    if args.workspace && args.crate_name.is_some() {
+        bail!("Cannot specify a crate with `--workspace`");
+    }
 
     let project_info = ProjectInfo::new()?;
-    let (crate_name, manifest) = if let Some(crate_name) = args.crate_name {
-        (
-            crate_name.clone(),
-            project_info
-                .manifests
-                .get(&crate_name)
-                .ok_or_else(|| anyhow!("No such crate `{crate_name}`"))?
-                .clone(),
-        )
+    let crates: Vec<&Crate> = if args.workspace {
+        project_info.crates.values().collect()
     } else {
-        let manifests = project_info.manifests;
-        if manifests.len() > 1 {
-            eprintln!("Multiple crates found:");
-            for (krate, _) in manifests {
-                eprintln!("\t{krate}");
+        vec![project_info.select_crate(args.crate_name.as_deref())?]
+    };
+
+    // Command line options take precedence over configuration in Cargo.toml
+    let cli_config = Config {
+        base_url: args.base_url.clone(),
+        asset_base_url: args.asset_base_url.clone(),
+        docs_rs: flag("docs-rs", args.docs_rs, args.no_docs_rs)?,
+        template: args.template.as_ref().map(PathBuf::from),
+        output: args.output.as_ref().map(PathBuf::from),
+        lib: args.lib.then_some(true),
+        bin: args.bin.clone(),
+        item: args.item.clone(),
+        unpin_std_docs: flag(
+            "unpin-std-docs",
+            args.unpin_std_docs,
+            args.no_unpin_std_docs,
+        )?,
+        plain_warnings: flag(
+            "plain-warnings",
+            args.plain_warnings,
+            args.no_plain_warnings,
+        )?,
+        ascii_punctuation: flag(
+            "ascii-punctuation",
+            args.ascii_punctuation,
+            args.no_ascii_punctuation,
+        )?,
+        flavor: args.flavor.as_deref().map(str::parse).transpose()?,
+        format: args.format.as_deref().map(str::parse).transpose()?,
+        deny_dropped_links: flag(
+            "deny-dropped-links",
+            args.deny_dropped_links,
+            args.no_deny_dropped_links,
+        )?,
+        exclude_sections: (!args.exclude_section.is_empty()).then(|| args.exclude_section.clone()),
+        rustdoc_args: (!args.rustdoc_args.is_empty()).then(|| args.rustdoc_args.clone()),
+    };
+
+    // One `cargo doc` run builds the whole workspace. Members' configs fall back to the
+    // workspace's, so only arguments that differ from it were set by the package
+    if args.workspace {
+        for krate in &crates {
+            if krate.config.rustdoc_args != project_info.config.rustdoc_args {
+                eprintln!(
+                    "Warning: ignoring `rustdoc-args` of `{}` in workspace mode; set them in \
+                     `[workspace.metadata.doc2readme]` instead",
+                    krate.name
+                );
             }
-            bail!("Could not select a crate");
         }
-        manifests
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow!("No crates found"))?
+    }
+
+    let build_config = cli_config.clone().or(if args.workspace {
+        project_info.config.clone()
+    } else {
+        crates[0].config.clone()
+    });
+    let mut cargo_doc_args = build_config.rustdoc_args.clone().unwrap_or_default();
+    if args.workspace {
+        // Members select their targets separately, and not all of them have the same targets, so
+        // document them all
+        cargo_doc_args.push("--workspace".into());
+    } else {
+        cargo_doc_args.extend(build_config.target()?.cargo_args());
+    }
+    let docs = if args.no_build {
+        Docs::default()
+    } else {
+        Docs::build(&cargo_doc_args, &project_info.doc_target_dir())?
     };
 
-    let doc_path = Path::new(".")
-        .join(project_info.target_dir)
-        .join(format!("doc/{}/index.html", crate_name.replace('-', "_")));
+    let mut up_to_date = true;
+    for krate in crates {
+        // Paths in Cargo.toml are relative to the crate's directory. Paths on the command line are
+        // too in workspace mode, but are otherwise relative to the working directory
+        let cli_dir = if args.workspace {
+            krate.dir.as_path()
+        } else {
+            Path::new("")
+        };
+        let mut config = cli_config
+            .clone()
+            .relative_to(cli_dir)
+            .or(krate.config.clone().relative_to(&krate.dir));
+        if args.workspace {
+            let extension = config.format.unwrap_or_default().extension();
+            config
+                .output
+                .get_or_insert_with(|| krate.dir.join(format!("README.{extension}")));
+        }
 
+        up_to_date &= generate_readme(&args, &config, &project_info, &docs, krate)?;
+    }
+
+    if !up_to_date {
+        bail!("README is out of date");
+    }
+
+    Ok(())
+}
+
+/// Generate the README for a single crate
+///
+/// Returns `false` if in `--check` mode and the output file is out of date
+fn generate_readme(
+    args: &BareArgs,
+    config: &Config,
+    project_info: &ProjectInfo,
+    docs: &Docs,
+    krate: &Crate,
+) -> Result<bool> {
+    let target_name = krate.target_name(&config.target()?)?;
+    let doc_path = docs
+        .index_path(target_name)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| project_info.doc_path(target_name));
     if doc_path.metadata().is_err() {
         bail!("Cannot find '{}'", doc_path.display());
     }
 
-    let html = fs::read_to_string(doc_path)?;
-    let mut markdown = convert::html_to_readme(
+    let page = if let Some(item) = &config.item {
+        let crate_doc_dir = doc_path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid doc path '{}'", doc_path.display()))?;
+        item::find_page(crate_doc_dir, item)?
+    } else {
+        ItemPage {
+            path: doc_path,
+            url_dir: String::new(),
+        }
+    };
+
+    // Relative links are relative to the page, not the crate root
+    let base_url = config
+        .base_url
+        .clone()
+        .or_else(|| {
+            config
+                .docs_rs
+                .unwrap_or_default()
+                .then(|| krate.docs_rs_url())
+        })
+        .map(|base_url| format!("{}/{}", base_url.trim_end_matches('/'), page.url_dir));
+
+    let html = fs::read_to_string(page.path)?;
+    let conversion = convert::convert_page(
         &html,
         Options {
-            base_url: args.base_url,
-            unpin_std_docs: args.unpin_std_docs,
+            base_url,
+            asset_base_url: config.asset_base_url.clone(),
+            unpin_std_docs: config.unpin_std_docs.unwrap_or_default(),
+            plain_warnings: config.plain_warnings.unwrap_or_default(),
+            ascii_punctuation: config.ascii_punctuation.unwrap_or_default(),
+            flavor: config.flavor.unwrap_or_default(),
+            format: config.format.unwrap_or_default(),
+            exclude_sections: config.exclude_sections.clone().unwrap_or_default(),
         },
     )?;
 
-    // Template markdown
-    if !args.no_template {
-        let mut templates = Environment::new();
-        let template = if let Some(template_path) = args.template {
+    // Rewriting relative links is routine, so those are only listed on request
+    let reported = conversion
+        .links
+        .iter()
+        .filter(|link| args.verbose || !matches!(link.change, LinkChange::Rewritten(_)))
+        .collect::<Vec<_>>();
+    if !reported.is_empty() {
+        eprintln!("Links changed in `{}`:", krate.name);
+        for link in reported {
+            eprintln!("  {link}");
+        }
+    }
+    let dropped = conversion
+        .links
+        .iter()
+        .filter(|link| matches!(link.change, LinkChange::Dropped(_)))
+        .count();
+    if dropped > 0 && config.deny_dropped_links.unwrap_or_default() {
+        bail!("{dropped} link(s) dropped from `{}`", krate.name);
+    }
+
+    // Template the README
+    let mut readme = if args.no_template {
+        conversion.readme
+    } else {
+        let default_template_path = krate.dir.join(DEFAULT_TEMPLATE_PATH);
+        let template = if let Some(template_path) = &config.template {
             fs::read_to_string(template_path)?
-        } else if Path::new(DEFAULT_TEMPLATE_PATH).is_file() {
-            fs::read_to_string(DEFAULT_TEMPLATE_PATH)?
+        } else if default_template_path.is_file() {
+            fs::read_to_string(default_template_path)?
         } else {
-            include_str!("DEFAULT_TEMPLATE.tpl").into()
+            template::default_template(config.format.unwrap_or_default()).into()
         };
-        templates.add_template("template", &template)?;
-        let template = templates.get_template("template")?;
-        markdown = template.render(context!(
-                crate => crate_name,
-                readme => markdown,
-                version => manifest.package.as_ref().map(|p|p.version.clone()),
-                license => manifest.package.as_ref().map(|p|p.license.clone()),
-                package => manifest.package.clone(),
-        ))?;
-    }
+        template::render(&template, &krate.name, &krate.manifest, &conversion)?
+    };
 
     // minjinja strips newlines, which is only sometimes what we want
-    if !markdown.ends_with('\n') {
-        markdown.push('\n');
+    if !readme.ends_with('\n') {
+        readme.push('\n');
     }
 
-    if let Some(output_file) = args.output {
+    if args.check {
+        let output_file = config
+            .output
+            .as_ref()
+            .ok_or_else(|| anyhow!("`--check` requires an output file"))?;
+        // A missing file is just out of date, but other errors shouldn't pass for an empty README
+        let existing = match fs::read_to_string(output_file) {
+            Ok(existing) => existing,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Cannot read '{}'", output_file.display()))
+            }
+        };
+        if existing != readme {
+            let output_file = output_file.display().to_string();
+            let diff = TextDiff::from_lines(&existing, &readme);
+            print!(
+                "{}",
+                diff.unified_diff()
+                    .header(&output_file, &format!("{output_file} (generated)"))
+            );
+            return Ok(false);
+        }
+    } else if let Some(output_file) = &config.output {
         let mut file = File::create(output_file)?;
-        file.write_all(markdown.as_bytes())?;
+        file.write_all(readme.as_bytes())?;
     } else {
-        print!("{}", markdown);
+        print!("{}", readme);
     }
 
-    Ok(())
+    Ok(true)
 }
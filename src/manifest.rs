@@ -1,69 +1,306 @@
+//! Discover the Cargo project in the current directory
+use crate::config::Config;
 use anyhow::{anyhow, bail, Result};
+use cargo_metadata::{MetadataCommand, Package, Target};
 use cargo_toml::Manifest;
-use std::{collections::HashMap, env, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+};
 
+// Target kinds that rustdoc documents as a library
+const LIB_KINDS: [&str; 6] = ["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+
+/// Which of a package's targets to document
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TargetSelection {
+    /// The library, then the binary named after the package, then any binary
+    #[default]
+    Default,
+    /// The library
+    Lib,
+    /// The binary with the given name
+    Bin(String),
+}
+
+impl TargetSelection {
+    /// Arguments to pass to `cargo doc` to document the selected target
+    pub fn cargo_args(&self) -> Vec<String> {
+        match self {
+            Self::Default => Vec::new(),
+            Self::Lib => vec!["--lib".into()],
+            Self::Bin(name) => vec!["--bin".into(), name.clone()],
+        }
+    }
+}
+
+/// A crate (package) in the project
+#[derive(Clone, Debug)]
+pub struct Crate {
+    /// The package name
+    pub name: String,
+    /// The package version
+    pub version: String,
+    /// The package's parsed `Cargo.toml`
+    pub manifest: Manifest,
+    /// Directory containing the package's `Cargo.toml`
+    pub dir: PathBuf,
+    /// Name of the library target, if any
+    pub lib_name: Option<String>,
+    /// Names of the binary targets
+    pub bin_names: Vec<String>,
+    /// Configuration from `[package.metadata.doc2readme]`, falling back to
+    /// `[workspace.metadata.doc2readme]`
+    pub config: Config,
+}
+
+impl Crate {
+    fn from_package(package: &Package, workspace_config: &Config) -> Result<Self> {
+        let dir = package
+            .manifest_path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid manifest path '{}'", package.manifest_path))?;
+
+        Ok(Self {
+            name: package.name.clone(),
+            version: package.version.to_string(),
+            manifest: Manifest::from_path(&package.manifest_path)?,
+            dir: dir.into(),
+            lib_name: package
+                .targets
+                .iter()
+                .find(|target| is_lib(target))
+                .map(|target| target.name.clone()),
+            bin_names: package
+                .targets
+                .iter()
+                .filter(|target| target.is_bin())
+                .map(|target| target.name.clone())
+                .collect(),
+            config: Config::from_metadata(&package.metadata)?.or(workspace_config.clone()),
+        })
+    }
+
+    /// Name of the selected target
+    pub fn target_name(&self, selection: &TargetSelection) -> Result<&str> {
+        match selection {
+            TargetSelection::Default => self
+                .lib_name
+                .as_deref()
+                .or_else(|| {
+                    self.bin_names
+                        .iter()
+                        .find(|name| **name == self.name)
+                        .or_else(|| self.bin_names.first())
+                        .map(String::as_str)
+                })
+                .ok_or_else(|| anyhow!("Crate `{}` has no lib or bin targets", self.name)),
+            TargetSelection::Lib => self
+                .lib_name
+                .as_deref()
+                .ok_or_else(|| anyhow!("Crate `{}` has no lib target", self.name)),
+            TargetSelection::Bin(bin) => self
+                .bin_names
+                .iter()
+                .find(|name| *name == bin)
+                .map(String::as_str)
+                .ok_or_else(|| anyhow!("Crate `{}` has no bin target `{bin}`", self.name)),
+        }
+    }
+
+    /// URL of this version of the crate's documentation on docs.rs
+    pub fn docs_rs_url(&self) -> String {
+        // docs.rs only documents libraries
+        format!(
+            "https://docs.rs/{}/{}/{}/",
+            self.name,
+            self.version,
+            self.lib_name
+                .as_ref()
+                .unwrap_or(&self.name)
+                .replace('-', "_")
+        )
+    }
+}
+
+/// Information about the Cargo project (or workspace) we're in
 #[derive(Clone, Debug)]
 pub struct ProjectInfo {
+    /// Path to the `target` directory
     pub target_dir: PathBuf,
-    pub manifests: HashMap<String, Manifest>,
+    /// All crates in the workspace, keyed by package name
+    pub crates: BTreeMap<String, Crate>,
+    /// Configuration from `[workspace.metadata.doc2readme]`
+    pub config: Config,
+    // Directory the project was discovered from, which picks the crate when none is named
+    working_dir: PathBuf,
 }
 
 impl ProjectInfo {
+    /// Discover the project containing the current directory
     pub fn new() -> Result<Self> {
-        let mut target_dir = None;
-        let mut manifests = None;
-
-        let mut current_path = env::current_dir()?;
-        while target_dir.is_none() || manifests.is_none() {
-            if current_path.parent().is_none() {
-                if manifests.is_none() {
-                    bail!("Not in a Cargo project directory");
-                } else {
-                    bail!("Could not find `target` directory.");
-                }
-            }
+        Self::discover(&env::current_dir()?)
+    }
 
-            let manifest_path = current_path.join("Cargo.toml");
-            if manifests.is_none() && manifest_path.is_file() {
-                let manifest = Manifest::from_path(manifest_path)?;
-
-                if let Some(workspace) = manifest.workspace {
-                    let mut map = HashMap::new();
-                    for member in workspace.members {
-                        let path = current_path.join(member);
-                        map.insert(
-                            path.file_name()
-                                .ok_or_else(|| {
-                                    anyhow!("Couldn't extract file name from '{}'", path.display())
-                                })?
-                                .to_string_lossy()
-                                .into_owned(),
-                            Manifest::from_path(path.join("Cargo.toml"))?,
-                        );
-                    }
-                    manifests = Some(map);
-                } else if let Some(ref package) = manifest.package {
-                    manifests = Some(HashMap::from([(package.name.clone(), manifest)]));
-                } else {
-                    bail!("Cargo.toml does not have a package section");
-                };
-            }
+    fn discover(working_dir: &Path) -> Result<Self> {
+        let metadata = MetadataCommand::new()
+            .current_dir(working_dir)
+            .no_deps()
+            .exec()?;
+        let config = Config::from_metadata(&metadata.workspace_metadata)?;
+
+        let crates = metadata
+            .workspace_packages()
+            .into_iter()
+            .map(|package| Ok((package.name.clone(), Crate::from_package(package, &config)?)))
+            .collect::<Result<_>>()?;
 
-            let target_path = current_path.join("target");
-            if target_dir.is_none() && target_path.is_dir() {
-                target_dir = Some(target_path);
+        Ok(Self {
+            target_dir: metadata.target_directory.into(),
+            crates,
+            config,
+            working_dir: working_dir.canonicalize()?,
+        })
+    }
+
+    /// Select a crate by name
+    ///
+    /// If no name is given, select the only crate in the project, or else the one the current
+    /// directory is in.
+    pub fn select_crate(&self, crate_name: Option<&str>) -> Result<&Crate> {
+        if let Some(crate_name) = crate_name {
+            return self
+                .crates
+                .get(crate_name)
+                .ok_or_else(|| anyhow!("No such crate `{crate_name}`"));
+        }
+
+        if self.crates.len() > 1 {
+            // Packages can be nested, so the innermost one wins
+            let containing = self
+                .crates
+                .values()
+                .filter(|krate| {
+                    krate
+                        .dir
+                        .canonicalize()
+                        .is_ok_and(|dir| self.working_dir.starts_with(dir))
+                })
+                .max_by_key(|krate| krate.dir.components().count());
+            if let Some(krate) = containing {
+                return Ok(krate);
             }
 
-            current_path = current_path
-                .parent()
-                .expect("Expected directory to have parent")
-                .into();
+            let crates: Vec<_> = self.crates.keys().map(String::as_str).collect();
+            bail!(
+                "Multiple crates found, could not select one:\n\t{}",
+                crates.join("\n\t")
+            );
         }
 
-        Ok(Self {
-            // These shouldn't panic since we're only exiting the loop if they're Some
-            target_dir: target_dir.expect("No target path"),
-            manifests: manifests.expect("No manifests"),
-        })
+        self.crates
+            .values()
+            .next()
+            .ok_or_else(|| anyhow!("No crates found"))
+    }
+
+    /// Target directory documentation is built in, inside the regular one
+    ///
+    /// Documentation is built with `--cfg doc2readme`, so it's kept apart from the regular
+    /// documentation. Pass this to [`Docs::build`](crate::build::Docs::build).
+    pub fn doc_target_dir(&self) -> PathBuf {
+        self.target_dir.join("doc2readme")
+    }
+
+    /// Default path to the rustdoc-generated `index.html` of a target, in the
+    /// [`doc_target_dir`](Self::doc_target_dir)
+    ///
+    /// Prefer [`Docs::index_path`](crate::build::Docs::index_path) when docs were built by
+    /// [`Docs::build`](crate::build::Docs::build), since that accounts for `--target`.
+    pub fn doc_path(&self, target_name: &str) -> PathBuf {
+        self.doc_target_dir()
+            .join("doc")
+            .join(target_name.replace('-', "_"))
+            .join("index.html")
+    }
+}
+
+fn is_lib(target: &Target) -> bool {
+    target
+        .kind
+        .iter()
+        .any(|kind| LIB_KINDS.contains(&kind.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn select_crate() -> Result<()> {
+        let workspace = TempDir::new()?;
+        fs::write(
+            workspace.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"first\", \"second\"]\nresolver = \"2\"\n",
+        )?;
+        for name in ["first", "second"] {
+            let dir = workspace.path().join(name);
+            fs::create_dir_all(dir.join("src"))?;
+            fs::write(
+                dir.join("Cargo.toml"),
+                format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+            )?;
+            fs::write(dir.join("src/lib.rs"), "")?;
+        }
+
+        let project_info = ProjectInfo::discover(workspace.path())?;
+        assert!(project_info.select_crate(None).is_err());
+        assert_eq!("first", project_info.select_crate(Some("first"))?.name);
+
+        let project_info = ProjectInfo::discover(&workspace.path().join("second/src"))?;
+        assert_eq!("second", project_info.select_crate(None)?.name);
+        assert_eq!("first", project_info.select_crate(Some("first"))?.name);
+        Ok(())
+    }
+
+    #[test]
+    fn target_name() -> Result<()> {
+        let krate = |lib_name: Option<&str>, bin_names: &[&str]| -> Result<Crate> {
+            Ok(Crate {
+                name: "tool".into(),
+                version: "0.1.0".into(),
+                manifest: Manifest::from_str("[package]\nname = \"tool\"\nversion = \"0.1.0\"")?,
+                dir: PathBuf::new(),
+                lib_name: lib_name.map(String::from),
+                bin_names: bin_names.iter().copied().map(String::from).collect(),
+                config: Default::default(),
+            })
+        };
+
+        let both = krate(Some("tool_lib"), &["other", "tool"])?;
+        assert_eq!("tool_lib", both.target_name(&TargetSelection::Default)?);
+        assert_eq!("tool_lib", both.target_name(&TargetSelection::Lib)?);
+        assert_eq!(
+            "other",
+            both.target_name(&TargetSelection::Bin("other".into()))?
+        );
+        assert!(both
+            .target_name(&TargetSelection::Bin("missing".into()))
+            .is_err());
+
+        // Without a lib, the bin named after the package comes first
+        let bins = krate(None, &["other", "tool"])?;
+        assert_eq!("tool", bins.target_name(&TargetSelection::Default)?);
+        assert!(bins.target_name(&TargetSelection::Lib).is_err());
+        let bins = krate(None, &["other"])?;
+        assert_eq!("other", bins.target_name(&TargetSelection::Default)?);
+
+        assert!(krate(None, &[])?
+            .target_name(&TargetSelection::Default)
+            .is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,874 @@
+// Renders rustdoc HTML as reStructuredText, AsciiDoc or plain text.
+//
+// html2md escapes Markdown syntax in all of the text it outputs, so these formats walk the HTML
+// themselves instead of going through html2md and its tag handlers.
+use crate::anchor_handler::resolve_link;
+use crate::convert::{
+    ascii_punctuation, replace_punctuation, LinkChange, LinkDiagnostic, Options, SECTION_MARK,
+};
+use crate::flavor::Flavor;
+use crate::format::Format;
+use crate::header_handler::Headings;
+use crate::image_handler::resolve_src;
+use scraper::{ElementRef, Node, Selector};
+use std::collections::HashMap;
+use url::Url;
+
+// Elements that are rendered as blocks of their own
+const BLOCK_ELEMENTS: [&str; 17] = [
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "pre",
+    "div",
+    "section",
+    "details",
+    "summary",
+    "ul",
+    "ol",
+    "blockquote",
+    "table",
+    "hr",
+];
+
+// Underline characters for reStructuredText headings, by level
+const RST_UNDERLINES: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+fn has_class(element: ElementRef, class: &str) -> bool {
+    element
+        .value()
+        .attr("class")
+        .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+}
+
+fn child_elements<'a>(element: ElementRef<'a>) -> impl Iterator<Item = ElementRef<'a>> {
+    element.children().filter_map(ElementRef::wrap)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Prefix every non-empty line
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{prefix}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Indent every line but the first, so it lines up after a list marker or directive
+fn hang(text: &str, width: usize) -> String {
+    match text.split_once('\n') {
+        Some((first, rest)) => format!("{first}\n{}", indent(rest, &" ".repeat(width))),
+        None => text.to_owned(),
+    }
+}
+
+// Surround `content` with markup, keeping any surrounding whitespace outside of it
+fn wrap(content: &str, open: &str, close: &str) -> String {
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return content.to_owned();
+    }
+    let start = &content[..content.len() - content.trim_start().len()];
+    let end = &content[content.trim_end().len()..];
+    format!("{start}{open}{trimmed}{close}{end}")
+}
+
+// Append inline content, without doubling up the space between two pieces
+fn push_inline(output: &mut String, inline: &str) {
+    if output.is_empty() || output.ends_with([' ', '\n']) {
+        output.push_str(inline.trim_start_matches(' '));
+    } else {
+        output.push_str(inline);
+    }
+}
+
+// If `element` is a footnote reference, get its label
+fn footnote_label<'a>(element: ElementRef<'a>) -> Option<&'a str> {
+    let mut children = child_elements(element);
+    let (Some(anchor), None) = (children.next(), children.next()) else {
+        return None;
+    };
+    let label = anchor.value().attr("href")?.strip_prefix("#fn")?;
+    (!label.is_empty()).then_some(label)
+}
+
+// The text of a heading, minus rustdoc's `§` anchor
+pub(crate) fn heading_text(element: ElementRef) -> String {
+    let text = element
+        .descendants()
+        .filter(|node| {
+            !node
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| has_class(ancestor, "doc-anchor"))
+        })
+        .filter_map(|node| node.value().as_text().map(|text| &**text))
+        .collect::<String>();
+    collapse_whitespace(&text)
+}
+
+// The images in a paragraph made up of nothing else, with the links around them
+fn images_only(element: ElementRef) -> Option<Vec<(ElementRef, Option<ElementRef>)>> {
+    let mut images = Vec::new();
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) if text.trim().is_empty() => {}
+            Node::Element(_) => {
+                let child = ElementRef::wrap(child)?;
+                match child.value().name() {
+                    "img" => images.push((child, None)),
+                    "a" => {
+                        let mut children = child.children().filter(|node| {
+                            !matches!(node.value(), Node::Text(text) if text.trim().is_empty())
+                        });
+                        let image = children.next().and_then(ElementRef::wrap)?;
+                        if image.value().name() != "img" || children.next().is_some() {
+                            return None;
+                        }
+                        images.push((image, Some(child)));
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        }
+    }
+    (!images.is_empty()).then_some(images)
+}
+
+// reStructuredText targets and AsciiDoc ids can be named almost anything, so borrow GFM's slugs
+// for them
+fn heading_flavor(format: Format) -> Flavor {
+    match format {
+        Format::Rst | Format::AsciiDoc => Flavor::Gfm,
+        _ => Flavor::CommonMark,
+    }
+}
+
+// Add a heading, returning the name of its target
+fn push_heading(
+    headings: &mut Headings,
+    format: Format,
+    level: usize,
+    text: &str,
+    id: Option<&str>,
+) -> Option<String> {
+    let heading = headings.push(level, text, id.map(String::from));
+    if let Some(anchor) = &mut heading.anchor {
+        // An empty name would make the target anonymous
+        if anchor.is_empty() {
+            *anchor = String::from("section");
+        }
+        // AsciiDoc ids have to start with a letter or underscore
+        if format == Format::AsciiDoc && !anchor.starts_with(|c: char| c.is_alphabetic()) {
+            anchor.insert(0, '_');
+        }
+    }
+    heading.anchor.clone()
+}
+
+// How an embedded reStructuredText link target refers to `url`
+fn rst_target(url: &str) -> String {
+    match url.strip_prefix('#') {
+        Some(anchor) => format!("{anchor}_"),
+        None => url.to_owned(),
+    }
+}
+
+// AsciiDoc has no reliable way to escape markup characters in text, so they're written as
+// attribute references, or as character references where there's no attribute for them
+pub(crate) fn asciidoc_escape(c: char) -> Option<&'static str> {
+    match c {
+        '*' => Some("{asterisk}"),
+        '+' => Some("{plus}"),
+        '`' => Some("{backtick}"),
+        '^' => Some("{caret}"),
+        '~' => Some("{tilde}"),
+        '\\' => Some("{backslash}"),
+        '[' => Some("{startsb}"),
+        ']' => Some("{endsb}"),
+        '<' => Some("{lt}"),
+        '_' => Some("&#95;"),
+        '#' => Some("&#35;"),
+        '{' => Some("&#123;"),
+        _ => None,
+    }
+}
+
+pub(crate) struct Renderer {
+    format: Format,
+    base_url: Option<Url>,
+    asset_base_url: Option<Url>,
+    unpin_std_docs: bool,
+    ascii_punctuation: bool,
+    // AsciiDoc footnotes are written where they're referenced, so definitions are collected first
+    footnotes: HashMap<String, String>,
+    list_depth: usize,
+    in_heading: bool,
+    // Levels to shift headings up by, so the top level ones are sections rather than subsections
+    heading_offset: usize,
+    // Every heading's target, collected up front since links can come before their heading
+    targets: Headings,
+    pub links: Vec<LinkDiagnostic>,
+    pub headings: Headings,
+}
+
+impl Renderer {
+    pub fn new(options: &Options, base_url: Option<Url>, asset_base_url: Option<Url>) -> Self {
+        Self {
+            format: options.format,
+            base_url,
+            asset_base_url,
+            unpin_std_docs: options.unpin_std_docs,
+            ascii_punctuation: options.ascii_punctuation,
+            footnotes: HashMap::new(),
+            list_depth: 0,
+            in_heading: false,
+            heading_offset: 0,
+            links: Vec::new(),
+            targets: Headings::new(heading_flavor(options.format)),
+            headings: Headings::new(heading_flavor(options.format)),
+        }
+    }
+
+    pub fn render(&mut self, docblock: ElementRef) -> String {
+        let selector = Selector::parse("h1, h2, h3, h4, h5, h6").expect("Valid selector");
+        self.heading_offset = docblock
+            .select(&selector)
+            .filter_map(|heading| heading.value().name()[1..].parse::<usize>().ok())
+            .min()
+            .map_or(0, |level| level - 1);
+        for heading in docblock.select(&selector) {
+            // AsciiDoc footnotes are written inline, so headings in them get no ids
+            let in_footnote = heading
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| has_class(ancestor, "footnotes"));
+            if self.format == Format::AsciiDoc && in_footnote {
+                continue;
+            }
+            let level: usize = heading.value().name()[1..].parse().expect("Heading level");
+            let text = self.punctuate(heading_text(heading));
+            push_heading(
+                &mut self.targets,
+                self.format,
+                level - self.heading_offset,
+                &text,
+                heading.value().attr("id"),
+            );
+        }
+
+        if self.format == Format::AsciiDoc {
+            let selector = Selector::parse("div.footnotes li").expect("Valid selector");
+            // Headings in footnotes don't start sections of their own
+            let headings = std::mem::take(&mut self.headings);
+            for item in docblock.select(&selector) {
+                if let Some(label) = item.value().attr("id").and_then(|id| id.strip_prefix("fn")) {
+                    let text = self.blocks(item).join(" ").replace(SECTION_MARK, "");
+                    self.footnotes.insert(label.to_owned(), text);
+                }
+            }
+            self.headings = headings;
+        }
+        self.blocks(docblock).join("\n\n")
+    }
+
+    // Collapse whitespace like a browser would, and escape markup
+    fn text(&self, text: &str, output: &mut String) {
+        let chars: Vec<char> = text.chars().collect();
+        for (i, &c) in chars.iter().enumerate() {
+            if c.is_whitespace() {
+                if !output.ends_with([' ', '\n']) {
+                    output.push(' ');
+                }
+                continue;
+            }
+            if self.ascii_punctuation {
+                if let Some(ascii) = ascii_punctuation(c) {
+                    output.push_str(ascii);
+                    continue;
+                }
+            }
+            if self.format == Format::Rst {
+                let escape = match c {
+                    '\\' | '*' | '`' | '|' => true,
+                    // `word_` is a reference
+                    '_' => !chars.get(i + 1).is_some_and(|c| c.is_alphanumeric()),
+                    _ => false,
+                };
+                if escape {
+                    output.push('\\');
+                }
+            }
+            if self.format == Format::AsciiDoc {
+                if let Some(escaped) = asciidoc_escape(c) {
+                    output.push_str(escaped);
+                    continue;
+                }
+            }
+            output.push(c);
+        }
+    }
+
+    // Text without any markup, for places that can't have nested markup
+    fn plain_text(&self, element: ElementRef) -> String {
+        self.punctuate(collapse_whitespace(&element.text().collect::<String>()))
+    }
+
+    // Undo smart punctuation, if asked to
+    fn punctuate(&self, text: String) -> String {
+        if !self.ascii_punctuation {
+            return text;
+        }
+        replace_punctuation(&text)
+    }
+
+    fn blocks(&mut self, element: ElementRef) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut paragraph = String::new();
+        let end_paragraph = |blocks: &mut Vec<String>, paragraph: &mut String| {
+            let text = paragraph.trim();
+            if !text.is_empty() {
+                blocks.push(text.to_owned());
+            }
+            paragraph.clear();
+        };
+
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text, &mut paragraph),
+                Node::Element(_) => {
+                    let child = ElementRef::wrap(child).expect("Node is an element");
+                    if BLOCK_ELEMENTS.contains(&child.value().name()) {
+                        end_paragraph(&mut blocks, &mut paragraph);
+                        blocks.extend(
+                            self.block(child)
+                                .into_iter()
+                                .filter(|block| !block.trim().is_empty()),
+                        );
+                    } else {
+                        let inline = self.inline(child);
+                        push_inline(&mut paragraph, &inline);
+                    }
+                }
+                _ => {}
+            }
+        }
+        end_paragraph(&mut blocks, &mut paragraph);
+
+        blocks
+    }
+
+    fn block(&mut self, element: ElementRef) -> Vec<String> {
+        let name = element.value().name();
+        match name {
+            "p" if self.format == Format::Rst => match images_only(element) {
+                Some(images) => images
+                    .into_iter()
+                    .map(|(image, link)| self.rst_image(image, link))
+                    .collect(),
+                None => self.blocks(element),
+            },
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level: usize = name[1..].parse().expect("Heading level");
+                vec![self.heading(element, level - self.heading_offset)]
+            }
+            "pre" => vec![self.code_block(element)],
+            "ul" | "ol" => vec![self.list(element)],
+            "blockquote" => {
+                let content = self.blocks(element).join("\n\n");
+                vec![match self.format {
+                    Format::AsciiDoc => format!("____\n{content}\n____"),
+                    _ => indent(&content, "    "),
+                }]
+            }
+            "div" if has_class(element, "warning") => {
+                let content = self.blocks(element).join("\n\n");
+                vec![match self.format {
+                    Format::Rst => format!(".. warning::\n\n{}", indent(&content, "   ")),
+                    Format::AsciiDoc => format!("[WARNING]\n====\n{content}\n===="),
+                    _ => format!("Warning:\n\n{}", indent(&content, "    ")),
+                }]
+            }
+            "div" if has_class(element, "footnotes") => self.footnote_definitions(element),
+            "table" => vec![self.table(element)],
+            "hr" => vec![match self.format {
+                Format::AsciiDoc => "'''".into(),
+                _ => "----".into(),
+            }],
+            _ => self.blocks(element),
+        }
+    }
+
+    fn inline_children(&mut self, element: ElementRef) -> String {
+        let mut output = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text, &mut output),
+                Node::Element(_) => {
+                    let child = ElementRef::wrap(child).expect("Node is an element");
+                    let inline = self.inline(child);
+                    push_inline(&mut output, &inline);
+                }
+                _ => {}
+            }
+        }
+        output
+    }
+
+    fn inline(&mut self, element: ElementRef) -> String {
+        match element.value().name() {
+            "em" | "i" => {
+                let content = self.inline_children(element);
+                match self.format {
+                    Format::Rst => wrap(&content, "*", "*"),
+                    Format::AsciiDoc => wrap(&content, "_", "_"),
+                    _ => content,
+                }
+            }
+            "strong" | "b" => {
+                let content = self.inline_children(element);
+                match self.format {
+                    Format::Rst => wrap(&content, "**", "**"),
+                    Format::AsciiDoc => wrap(&content, "*", "*"),
+                    _ => content,
+                }
+            }
+            "del" | "s" => {
+                let content = self.inline_children(element);
+                match self.format {
+                    Format::AsciiDoc => wrap(&content, "[.line-through]#", "#"),
+                    _ => content,
+                }
+            }
+            "code" | "kbd" | "samp" => {
+                let code = collapse_whitespace(&element.text().collect::<String>());
+                if code.is_empty() {
+                    return code;
+                }
+                match self.format {
+                    Format::Rst => format!("``{code}``"),
+                    Format::AsciiDoc => format!("`+{code}+`"),
+                    _ => code,
+                }
+            }
+            "br" => match self.format {
+                Format::AsciiDoc => " +\n".into(),
+                Format::Text => "\n".into(),
+                _ => " ".into(),
+            },
+            "sup" => {
+                if let Some(label) = footnote_label(element) {
+                    return match self.format {
+                        Format::Rst => format!("\\ [{label}]_"),
+                        Format::AsciiDoc => match self.footnotes.get(label) {
+                            Some(text) => format!("footnote:[{}]", text.replace(']', "\\]")),
+                            None => format!("[{label}]"),
+                        },
+                        _ => format!("[{label}]"),
+                    };
+                }
+                let content = self.inline_children(element);
+                match self.format {
+                    Format::Rst => format!("\\ :sup:`{}`", content.trim()),
+                    Format::AsciiDoc => wrap(&content, "^", "^"),
+                    _ => wrap(&content, "^", ""),
+                }
+            }
+            "sub" => {
+                let content = self.inline_children(element);
+                match self.format {
+                    Format::Rst => format!("\\ :sub:`{}`", content.trim()),
+                    Format::AsciiDoc => wrap(&content, "~", "~"),
+                    _ => content,
+                }
+            }
+            "a" => self.link(element),
+            "img" => self.image(element, None),
+            _ => self.inline_children(element),
+        }
+    }
+
+    // The URL a link should point to, if it's kept
+    fn link_target(&mut self, element: ElementRef, text: &str) -> Option<String> {
+        let href = element.value().attr("href").unwrap_or_default();
+        // Headings link to themselves
+        if self.in_heading && href.starts_with('#') {
+            return None;
+        }
+
+        let (url, change) = match href.strip_prefix('#') {
+            Some(fragment) => self.fragment_target(fragment),
+            None => resolve_link(href, self.base_url.as_ref(), self.unpin_std_docs),
+        };
+        if let Some(change) = change {
+            self.links.push(LinkDiagnostic {
+                text: collapse_whitespace(text),
+                href: href.to_owned(),
+                change,
+            });
+        }
+        url
+    }
+
+    // Point an in-page link at the target written before its heading, since rustdoc's heading ids
+    // don't always match
+    fn fragment_target(&self, fragment: &str) -> (Option<String>, Option<LinkChange>) {
+        if !matches!(self.format, Format::Rst | Format::AsciiDoc) {
+            return (
+                None,
+                Some(LinkChange::Dropped(format!(
+                    "{} has no heading anchors",
+                    self.format
+                ))),
+            );
+        }
+        match self
+            .targets
+            .find(fragment)
+            .and_then(|heading| heading.anchor.as_ref())
+        {
+            Some(anchor) => {
+                let change =
+                    (anchor != fragment).then(|| LinkChange::Rewritten(format!("#{anchor}")));
+                (Some(format!("#{anchor}")), change)
+            }
+            None => (
+                None,
+                Some(LinkChange::Dropped("no heading with this anchor".into())),
+            ),
+        }
+    }
+
+    fn link(&mut self, element: ElementRef) -> String {
+        // Ignore tooltips, rustdoc's `§` heading anchors and footnote back-references
+        let href = element.value().attr("href").unwrap_or_default();
+        if has_class(element, "tooltip")
+            || has_class(element, "doc-anchor")
+            || href.starts_with("#fnref")
+        {
+            return String::new();
+        }
+
+        let text = self.inline_children(element);
+        let Some(url) = self.link_target(element, &text) else {
+            return text;
+        };
+
+        if let [image] = child_elements(element).collect::<Vec<_>>()[..] {
+            if image.value().name() == "img" {
+                return self.image(image, Some(&url));
+            }
+        }
+
+        match self.format {
+            Format::Rst => {
+                let text = self.plain_text(element).replace('`', "\\`");
+                if text.is_empty() {
+                    url
+                } else {
+                    format!("`{} <{}>`__", text.replace('<', "\\<"), rst_target(&url))
+                }
+            }
+            Format::AsciiDoc => match url.strip_prefix('#') {
+                Some(anchor) => wrap(&text, &format!("<<{anchor},"), ">>"),
+                None => wrap(&text, &format!("link:{url}["), "]"),
+            },
+            _ => {
+                let label = text.trim();
+                if label.is_empty() || label == url {
+                    url
+                } else {
+                    wrap(&text, "", &format!(" ({url})"))
+                }
+            }
+        }
+    }
+
+    fn image(&mut self, element: ElementRef, link: Option<&str>) -> String {
+        let alt = element.value().attr("alt").unwrap_or_default();
+        match self.format {
+            Format::AsciiDoc => {
+                let Some(src) = self.image_src(element) else {
+                    return alt.to_owned();
+                };
+                let mut attrs = vec![format!("\"{}\"", alt.replace('"', "\\\""))];
+                for name in ["width", "height"] {
+                    if let Some(value) = element.value().attr(name) {
+                        attrs.push(format!("{name}={value}"));
+                    }
+                }
+                if let Some(link) = link {
+                    attrs.push(format!("link=\"{link}\""));
+                }
+                format!("image:{src}[{}]", attrs.join(","))
+            }
+            // Images can't be inline in reStructuredText without substitutions, and plain text
+            // has no images at all, so fall back to the alt text
+            Format::Rst => match link {
+                Some(link) if !alt.is_empty() => {
+                    format!("`{} <{}>`__", alt.replace('`', "\\`"), rst_target(link))
+                }
+                _ => alt.to_owned(),
+            },
+            _ => alt.to_owned(),
+        }
+    }
+
+    // The URL an image should be loaded from, if it's kept
+    fn image_src(&mut self, image: ElementRef) -> Option<String> {
+        let href = image.value().attr("src").unwrap_or_default();
+        let (src, change) = resolve_src(href, self.asset_base_url.as_ref());
+        if let Some(change) = change {
+            self.links.push(LinkDiagnostic {
+                text: collapse_whitespace(image.value().attr("alt").unwrap_or_default()),
+                href: href.to_owned(),
+                change,
+            });
+        }
+        src
+    }
+
+    fn rst_image(&mut self, image: ElementRef, link: Option<ElementRef>) -> String {
+        let Some(src) = self.image_src(image) else {
+            return image.value().attr("alt").unwrap_or_default().to_owned();
+        };
+        let mut directive = format!(".. image:: {src}");
+        for name in ["alt", "width", "height"] {
+            if let Some(value) = image.value().attr(name).filter(|value| !value.is_empty()) {
+                directive.push_str(&format!("\n   :{name}: {value}"));
+            }
+        }
+        let alt = image.value().attr("alt").unwrap_or_default();
+        if let Some(url) = link.and_then(|link| self.link_target(link, alt)) {
+            // Unlike embedded targets, image targets need quoting for names with punctuation
+            let target = match url.strip_prefix('#') {
+                Some(anchor) => format!("`{anchor}`_"),
+                None => url,
+            };
+            directive.push_str(&format!("\n   :target: {target}"));
+        }
+        directive
+    }
+
+    fn heading(&mut self, element: ElementRef, level: usize) -> String {
+        let text = self.punctuate(heading_text(element));
+        let target = push_heading(
+            &mut self.headings,
+            self.format,
+            level,
+            &text,
+            element.value().attr("id"),
+        );
+
+        self.in_heading = true;
+        let text = collapse_whitespace(&self.inline_children(element));
+        self.in_heading = false;
+
+        let underline = |c: char| c.to_string().repeat(text.chars().count());
+        let heading = match self.format {
+            Format::Rst => format!(
+                ".. _{}:\n\n{text}\n{}",
+                target.unwrap_or_default(),
+                underline(RST_UNDERLINES[level - 1])
+            ),
+            // Titles can repeat, so each heading gets an explicit id for links to point at. Level 0
+            // is the document title
+            Format::AsciiDoc => format!(
+                "[[{}]]\n{} {text}",
+                target.unwrap_or_default(),
+                "=".repeat((level + 1).min(6))
+            ),
+            _ => format!("{text}\n{}", underline(if level == 1 { '=' } else { '-' })),
+        };
+        format!("{SECTION_MARK}{heading}")
+    }
+
+    fn code_block(&self, element: ElementRef) -> String {
+        let code: String = element.text().collect();
+        let code = code.trim_end_matches('\n');
+        let language = std::iter::once(element)
+            .chain(child_elements(element))
+            .filter_map(|element| element.value().attr("class"))
+            .flat_map(str::split_whitespace)
+            .find_map(|class| {
+                if class == "rust" {
+                    Some("rust")
+                } else {
+                    class.strip_prefix("language-")
+                }
+            });
+
+        match self.format {
+            Format::Rst => match language {
+                Some(language) => {
+                    format!(".. code-block:: {language}\n\n{}", indent(code, "    "))
+                }
+                None => format!("::\n\n{}", indent(code, "    ")),
+            },
+            Format::AsciiDoc => {
+                let source = language
+                    .map(|language| format!("[source,{language}]\n"))
+                    .unwrap_or_default();
+                format!("{source}----\n{code}\n----")
+            }
+            _ => indent(code, "    "),
+        }
+    }
+
+    fn list(&mut self, element: ElementRef) -> String {
+        let ordered = element.value().name() == "ol";
+        let start: usize = element
+            .value()
+            .attr("start")
+            .and_then(|start| start.parse().ok())
+            .unwrap_or(1);
+
+        self.list_depth += 1;
+        let mut items = Vec::new();
+        for (i, item) in child_elements(element)
+            .filter(|child| child.value().name() == "li")
+            .enumerate()
+        {
+            let marker = match (self.format, ordered) {
+                (Format::AsciiDoc, true) => ".".repeat(self.list_depth),
+                (Format::AsciiDoc, false) => "*".repeat(self.list_depth),
+                (_, true) => format!("{}.", start + i),
+                (_, false) => "-".into(),
+            };
+            let blocks = self.blocks(item);
+            items.push(self.list_item(&marker, &blocks));
+        }
+        self.list_depth -= 1;
+
+        // Items with several blocks need blank lines between them
+        let loose = self.format != Format::AsciiDoc && items.iter().any(|item| item.contains('\n'));
+        items.join(if loose { "\n\n" } else { "\n" })
+    }
+
+    fn list_item(&self, marker: &str, blocks: &[String]) -> String {
+        if self.format != Format::AsciiDoc {
+            return hang(
+                &format!("{marker} {}", blocks.join("\n\n")),
+                marker.len() + 1,
+            );
+        }
+
+        let mut item = marker.to_owned();
+        for (i, block) in blocks.iter().enumerate() {
+            // Nested lists attach to the item by themselves, other blocks need a continuation
+            let nested = ["*", "."]
+                .iter()
+                .any(|m| block.starts_with(&format!("{} ", m.repeat(self.list_depth + 1))));
+            item.push_str(match (i, nested) {
+                (0, false) => " ",
+                (_, true) => "\n",
+                (_, false) => "\n+\n",
+            });
+            item.push_str(block);
+        }
+        item
+    }
+
+    fn footnote_definitions(&mut self, element: ElementRef) -> Vec<String> {
+        // AsciiDoc footnotes were already written where they're referenced
+        if self.format == Format::AsciiDoc {
+            return Vec::new();
+        }
+
+        let selector = Selector::parse("li").expect("Valid selector");
+        let mut definitions = Vec::new();
+        for item in element.select(&selector) {
+            let Some(label) = item.value().attr("id").and_then(|id| id.strip_prefix("fn")) else {
+                continue;
+            };
+            let text = self.blocks(item).join("\n\n");
+            definitions.push(match self.format {
+                Format::Rst => hang(&format!(".. [{label}] {text}"), 3),
+                _ => hang(&format!("[{label}] {text}"), label.len() + 3),
+            });
+        }
+        definitions
+    }
+
+    fn table(&mut self, element: ElementRef) -> String {
+        let selector = Selector::parse("tr").expect("Valid selector");
+        let mut rows = Vec::new();
+        let mut header = false;
+        for (i, row) in element.select(&selector).enumerate() {
+            let cells = child_elements(row)
+                .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                .collect::<Vec<_>>();
+            if i == 0 {
+                header = cells.iter().any(|cell| cell.value().name() == "th")
+                    || row
+                        .parent()
+                        .and_then(ElementRef::wrap)
+                        .is_some_and(|parent| parent.value().name() == "thead");
+            }
+            let mut row = Vec::new();
+            for cell in cells {
+                row.push(self.blocks(cell).join(" "));
+            }
+            if !row.is_empty() {
+                rows.push(row);
+            }
+        }
+
+        match self.format {
+            Format::Rst => {
+                let mut table = String::from(".. list-table::\n");
+                if header {
+                    table.push_str("   :header-rows: 1\n");
+                }
+                table.push('\n');
+                for row in rows {
+                    for (i, cell) in row.iter().enumerate() {
+                        let marker = if i == 0 { "   * -" } else { "     -" };
+                        table.push_str(format!("{marker} {cell}").trim_end());
+                        table.push('\n');
+                    }
+                }
+                table.trim_end().to_owned()
+            }
+            Format::AsciiDoc => {
+                let mut table = String::new();
+                if header {
+                    table.push_str("[options=\"header\"]\n");
+                }
+                table.push_str("|===\n");
+                for (i, row) in rows.iter().enumerate() {
+                    let cells = row
+                        .iter()
+                        .map(|cell| format!("| {}", cell.replace('|', "\\|")))
+                        .collect::<Vec<_>>();
+                    table.push_str(&cells.join(" "));
+                    table.push('\n');
+                    if i == 0 && header {
+                        table.push('\n');
+                    }
+                }
+                table.push_str("|===");
+                table
+            }
+            _ => rows
+                .iter()
+                .map(|row| row.join(" | "))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use crate::anchor_handler::resolve_link;
+use crate::convert::{LinkChange, LinkDiagnostic};
+use crate::flavor::Flavor;
+use crate::image_handler::resolve_src;
+use html2md::{StructuredPrinter, TagHandler, TagHandlerFactory};
+use markup5ever_rcdom::{Handle, NodeData};
+use std::{cell::RefCell, rc::Rc};
+use url::Url;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Alignment {
+    #[default]
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    fn from_attrs(style: Option<&str>, align: Option<&str>) -> Self {
+        let style_align = style.and_then(|style| {
+            style.split(';').find_map(|declaration| {
+                let (property, value) = declaration.split_once(':')?;
+                (property.trim() == "text-align").then_some(value.trim())
+            })
+        });
+        match style_align.or(align) {
+            Some("left") => Self::Left,
+            Some("center") => Self::Center,
+            Some("right") => Self::Right,
+            _ => Self::None,
+        }
+    }
+
+    fn delimiter(self) -> &'static str {
+        match self {
+            Self::None => "---",
+            Self::Left => ":--",
+            Self::Center => ":-:",
+            Self::Right => "--:",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Table {
+    // Column alignments, taken from the first row
+    alignments: Vec<Alignment>,
+    rows: usize,
+}
+
+// Tables currently being converted, innermost last
+type TableStack = Rc<RefCell<Vec<Table>>>;
+
+// Elements that don't have a closing tag
+const VOID_ELEMENTS: [&str; 4] = ["br", "hr", "img", "wbr"];
+
+/// How links and images are resolved in HTML that's copied as is
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HtmlLinks {
+    pub flavor: Flavor,
+    pub base_url: Option<Url>,
+    pub asset_base_url: Option<Url>,
+    pub unpin_std_docs: bool,
+    pub links: Rc<RefCell<Vec<LinkDiagnostic>>>,
+}
+
+impl HtmlLinks {
+    // The URL a link or image should have, if it's kept
+    fn resolve(&self, name: &str, href: &str, text: String) -> Option<String> {
+        let (url, change) = match name {
+            "img" => resolve_src(href, self.asset_base_url.as_ref()),
+            _ if !href.starts_with('#') => {
+                resolve_link(href, self.base_url.as_ref(), self.unpin_std_docs)
+            }
+            _ if self.flavor.heading_anchors() => (Some(href.to_owned()), None),
+            _ => (
+                None,
+                Some(LinkChange::Dropped(format!(
+                    "{} has no heading anchors",
+                    self.flavor
+                ))),
+            ),
+        };
+        if let Some(change) = change {
+            self.links.borrow_mut().push(LinkDiagnostic {
+                text,
+                href: href.to_owned(),
+                change,
+            });
+        }
+        url
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn text_content(node: &Handle, text: &mut String) {
+    match node.data {
+        NodeData::Text { ref contents } => text.push_str(&contents.borrow()),
+        _ => {
+            for child in node.children.borrow().iter() {
+                text_content(child, text);
+            }
+        }
+    }
+}
+
+// Serialize a node back to HTML, for flavors without tables and for tags html2md keeps as HTML.
+// With `links`, links and images are resolved like everywhere else, and dropped ones are replaced
+// by their text
+pub(crate) fn write_html(node: &Handle, html: &mut String, links: Option<&HtmlLinks>) {
+    match node.data {
+        NodeData::Text { ref contents } => {
+            let text = contents.borrow();
+            // Blank lines would end the HTML block early
+            if text.trim().is_empty() {
+                html.push_str(if text.contains('\n') { "\n" } else { " " });
+            } else {
+                html.push_str(&escape_text(&text));
+            }
+        }
+        NodeData::Element {
+            ref name,
+            ref attrs,
+            ..
+        } => {
+            let name = name.local.to_string();
+            let mut attrs = attrs
+                .borrow()
+                .iter()
+                .map(|attr| (attr.name.local.to_string(), attr.value.to_string()))
+                .collect::<Vec<_>>();
+            // The order is lost when the page is parsed, so sort them to keep the output stable
+            attrs.sort();
+
+            let url_attr = match name.as_str() {
+                "a" => Some("href"),
+                "img" => Some("src"),
+                _ => None,
+            };
+            if let Some((links, url_attr)) = links.zip(url_attr) {
+                let value = |attr_name: &str| {
+                    attrs
+                        .iter()
+                        .find(|(name, _)| name == attr_name)
+                        .map(|(_, value)| value.clone())
+                };
+                if let Some(url) = value(url_attr) {
+                    let text = if name == "img" {
+                        value("alt").unwrap_or_default()
+                    } else {
+                        let mut text = String::new();
+                        text_content(node, &mut text);
+                        text.split_whitespace().collect::<Vec<_>>().join(" ")
+                    };
+                    match links.resolve(&name, &url, text.clone()) {
+                        Some(resolved) => {
+                            if let Some((_, value)) =
+                                attrs.iter_mut().find(|(name, _)| name == url_attr)
+                            {
+                                *value = resolved;
+                            }
+                        }
+                        None if name == "img" => {
+                            html.push_str(&escape_text(&text));
+                            return;
+                        }
+                        None => {
+                            for child in node.children.borrow().iter() {
+                                write_html(child, html, Some(links));
+                            }
+                            return;
+                        }
+                    }
+                }
+            }
+
+            html.push('<');
+            html.push_str(&name);
+            for (attr_name, value) in attrs {
+                html.push_str(&format!(
+                    " {attr_name}=\"{}\"",
+                    value.replace('&', "&amp;").replace('"', "&quot;")
+                ));
+            }
+            html.push('>');
+            if !VOID_ELEMENTS.contains(&name.as_str()) {
+                for child in node.children.borrow().iter() {
+                    write_html(child, html, links);
+                }
+                html.push_str(&format!("</{name}>"));
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) struct TableHandler {
+    tables: TableStack,
+    links: HtmlLinks,
+    tag: String,
+    start_pos: usize,
+    skip_descendants: bool,
+}
+
+impl TableHandler {
+    fn trim_trailing_spaces(printer: &mut StructuredPrinter) {
+        let len = printer.data.trim_end_matches([' ', '\t']).len();
+        printer.data.truncate(len);
+    }
+}
+
+impl TagHandler for TableHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let NodeData::Element {
+            ref name,
+            ref attrs,
+            ..
+        } = tag.data
+        else {
+            return;
+        };
+        self.tag = name.local.to_string();
+
+        match self.tag.as_ref() {
+            "table" => {
+                // Table should have its own paragraph
+                if !printer.data.ends_with('\n') {
+                    printer.insert_newline();
+                }
+                printer.insert_newline();
+                if !self.links.flavor.tables() {
+                    // Raw HTML is the only way to keep a table
+                    let mut html = String::new();
+                    write_html(tag, &mut html, Some(&self.links));
+                    printer.append_str(&html);
+                    self.skip_descendants = true;
+                    return;
+                }
+                self.tables.borrow_mut().push(Default::default());
+            }
+            "tr" => {
+                Self::trim_trailing_spaces(printer);
+                printer.append_str("|");
+            }
+            "th" | "td" => {
+                let attrs = attrs.borrow();
+                let attr = |name: &str| {
+                    attrs
+                        .iter()
+                        .find(|attr| attr.name.local.to_string() == name)
+                        .map(|attr| attr.value.to_string())
+                };
+                if let Some(table) = self.tables.borrow_mut().last_mut() {
+                    if table.rows == 0 {
+                        table.alignments.push(Alignment::from_attrs(
+                            attr("style").as_deref(),
+                            attr("align").as_deref(),
+                        ));
+                    }
+                }
+
+                Self::trim_trailing_spaces(printer);
+                printer.append_str(" ");
+                self.start_pos = printer.data.len();
+            }
+            _ => {}
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        self.skip_descendants
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        match self.tag.as_ref() {
+            "table" => {
+                if !self.skip_descendants {
+                    self.tables.borrow_mut().pop();
+                }
+                printer.insert_newline();
+            }
+            "tr" => {
+                Self::trim_trailing_spaces(printer);
+                printer.insert_newline();
+
+                let mut tables = self.tables.borrow_mut();
+                let Some(table) = tables.last_mut() else {
+                    return;
+                };
+                table.rows += 1;
+
+                // GFM tables always have a header row, so treat the first row as one
+                if table.rows == 1 {
+                    printer.append_str("|");
+                    for alignment in &table.alignments {
+                        printer.append_str(&format!(" {} |", alignment.delimiter()));
+                    }
+                    printer.insert_newline();
+                }
+            }
+            "th" | "td" => {
+                // Cells must fit on one line, and pipes would end the cell early
+                let cell = printer.data.split_off(self.start_pos);
+                let cell = cell
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .replace('|', "\\|");
+                if cell.is_empty() {
+                    printer.append_str("|");
+                } else {
+                    printer.append_str(&cell);
+                    printer.append_str(" |");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TableHandlerFactory {
+    tables: TableStack,
+    links: HtmlLinks,
+}
+
+impl TableHandlerFactory {
+    pub fn new(links: HtmlLinks) -> Self {
+        Self {
+            links,
+            ..Default::default()
+        }
+    }
+}
+
+impl TagHandlerFactory for TableHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(TableHandler {
+            tables: self.tables.clone(),
+            links: self.links.clone(),
+            tag: Default::default(),
+            start_pos: Default::default(),
+            skip_descendants: false,
+        })
+    }
+}
@@ -0,0 +1,180 @@
+//! Render a README from a template
+use crate::{convert::Conversion, format::Format};
+use anyhow::Result;
+use cargo_toml::{Inheritable, Manifest};
+use minijinja::{context, Environment};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The template used when none is provided
+pub const DEFAULT_TEMPLATE: &str = include_str!("DEFAULT_TEMPLATE.tpl");
+/// The template used for reStructuredText when none is provided
+pub const DEFAULT_RST_TEMPLATE: &str = include_str!("DEFAULT_TEMPLATE.rst.tpl");
+/// The template used for AsciiDoc when none is provided
+pub const DEFAULT_ASCIIDOC_TEMPLATE: &str = include_str!("DEFAULT_TEMPLATE.adoc.tpl");
+/// The template used for plain text when none is provided
+pub const DEFAULT_TEXT_TEMPLATE: &str = include_str!("DEFAULT_TEMPLATE.txt.tpl");
+
+/// The default template for a format
+pub fn default_template(format: Format) -> &'static str {
+    match format {
+        Format::Markdown => DEFAULT_TEMPLATE,
+        Format::Rst => DEFAULT_RST_TEMPLATE,
+        Format::AsciiDoc => DEFAULT_ASCIIDOC_TEMPLATE,
+        Format::Text => DEFAULT_TEXT_TEMPLATE,
+    }
+}
+
+/// A dependency, as exposed to templates
+#[derive(Serialize)]
+struct Dependency<'a> {
+    name: &'a str,
+    // The name of the crate depended on, which differs from `name` if it was renamed
+    package: &'a str,
+    // `None` for git and path dependencies without a version, and for unresolved workspace ones
+    version: Option<&'a str>,
+    optional: bool,
+    features: &'a [String],
+}
+
+// Workspace inheritance is resolved when the manifest is loaded from a path, so fields are only
+// left inherited in manifests loaded some other way
+fn resolved<T>(value: &Inheritable<T>) -> Option<&T> {
+    value.get().ok()
+}
+
+/// Render `template` with the converted README and the crate's manifest data
+pub fn render(
+    template: &str,
+    crate_name: &str,
+    manifest: &Manifest,
+    conversion: &Conversion,
+) -> Result<String> {
+    let mut templates = Environment::new();
+    // Heading underlines for reStructuredText and plain text, e.g. `{{ crate | underline("=") }}`
+    templates.add_filter("underline", |value: String, character: String| {
+        character.repeat(value.chars().count())
+    });
+    templates.add_template("template", template)?;
+    let template = templates.get_template("template")?;
+
+    // Sections by heading text, keeping the first of any duplicates
+    let mut sections = BTreeMap::new();
+    for section in &conversion.sections {
+        if let Some(heading) = &section.heading {
+            sections
+                .entry(heading.text.as_str())
+                .or_insert(section.text.as_str());
+        }
+    }
+    // Only the top level sections, so together they make up the whole README
+    let top_level = conversion
+        .headings
+        .iter()
+        .map(|heading| heading.level)
+        .min();
+    let section_list = conversion
+        .sections
+        .iter()
+        .filter(|section| section.heading.as_ref().map(|heading| heading.level) <= top_level)
+        .collect::<Vec<_>>();
+
+    let package = manifest.package.as_ref();
+    let text = |field: fn(&cargo_toml::Package) -> &Option<Inheritable<String>>| {
+        package.and_then(|package| field(package).as_ref().and_then(resolved))
+    };
+    let list = |field: fn(&cargo_toml::Package) -> &Inheritable<Vec<String>>| {
+        package
+            .and_then(|package| resolved(field(package)))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let dependencies = manifest
+        .dependencies
+        .iter()
+        .map(|(name, dependency)| Dependency {
+            name,
+            package: dependency.package().unwrap_or(name),
+            version: match dependency {
+                cargo_toml::Dependency::Simple(version) => Some(version),
+                cargo_toml::Dependency::Detailed(detail) => detail.version.as_deref(),
+                cargo_toml::Dependency::Inherited(_) => None,
+            },
+            optional: dependency.optional(),
+            features: dependency.req_features(),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(template.render(context!(
+            crate => crate_name,
+            readme => conversion.readme,
+            toc => conversion.toc,
+            headings => conversion.headings,
+            sections => sections,
+            section_list => section_list,
+            version => package.and_then(|package| resolved(&package.version)),
+            license => text(|package| &package.license),
+            description => text(|package| &package.description),
+            repository => text(|package| &package.repository),
+            homepage => text(|package| &package.homepage),
+            documentation => text(|package| &package.documentation),
+            rust_version => text(|package| &package.rust_version),
+            edition => package.and_then(|package| resolved(&package.edition)),
+            authors => list(|package| &package.authors),
+            keywords => list(|package| &package.keywords),
+            categories => list(|package| &package.categories),
+            features => manifest.features,
+            dependencies => dependencies,
+            package => manifest.package.clone(),
+    ))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::{convert_page, Options};
+
+    #[test]
+    fn render() {
+        let manifest = Manifest::from_str(
+            r#"
+[package]
+name = "foo"
+version.workspace = true
+edition = "2021"
+license.workspace = true
+description = "Does things"
+
+[dependencies]
+serde = { version = "1.0", optional = true, features = ["derive"] }
+json = { package = "serde_json", version = "1" }
+shared = { workspace = true }
+"#,
+        )
+        .unwrap();
+        let conversion = convert_page(
+            "<div class='docblock'><p>Intro</p><h2 id='usage'>Usage</h2><p>Use it</p></div>",
+            Options::default(),
+        )
+        .unwrap();
+        let template = r#"{{ crate }} {{ version or "(inherited)" }}, {{ edition }}, {{ license or "(inherited)" }}
+{{ description }}
+{% for dependency in dependencies -%}
+* {{ dependency.name }}: {{ dependency.package }} {{ dependency.version or "*" }}
+{%- if dependency.optional %} (optional, {{ dependency.features | join(", ") }}){% endif %}
+{% endfor -%}
+{{ sections["Usage"] }}"#;
+
+        assert_eq!(
+            "foo (inherited), 2021, (inherited)
+Does things
+* json: serde_json 1
+* serde: serde 1.0 (optional, derive)
+* shared: shared *
+## Usage
+
+Use it",
+            super::render(template, "foo", &manifest, &conversion).unwrap()
+        );
+    }
+}
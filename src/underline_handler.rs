@@ -0,0 +1,36 @@
+use html2md::{StructuredPrinter, TagHandler, TagHandlerFactory};
+use markup5ever_rcdom::Handle;
+
+/// Writes underlined and inserted text as strong emphasis, since Markdown has no underline
+///
+/// html2md would write it as `__`, so this keeps strong emphasis to a single marker.
+#[derive(Default)]
+pub(crate) struct UnderlineHandler {
+    start_pos: usize,
+}
+
+impl TagHandler for UnderlineHandler {
+    fn handle(&mut self, _tag: &Handle, printer: &mut StructuredPrinter) {
+        self.start_pos = printer.data.len();
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        // Keep surrounding whitespace outside of the markers, like html2md does for emphasis
+        let Some(offset) = printer.data[self.start_pos..].find(|c: char| !c.is_whitespace()) else {
+            return;
+        };
+        let start = self.start_pos + offset;
+        let end = printer.data.trim_end().len();
+        printer.data.insert_str(end, "**");
+        printer.data.insert_str(start, "**");
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UnderlineHandlerFactory;
+
+impl TagHandlerFactory for UnderlineHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::<UnderlineHandler>::default()
+    }
+}
@@ -0,0 +1,95 @@
+use html2md::{StructuredPrinter, TagHandler, TagHandlerFactory};
+use markup5ever_rcdom::{Handle, NodeData};
+use std::rc::Rc;
+
+// Converts rustdoc's `<div class="warning">` to a GitHub alert, or a plain blockquote. Other
+// `div`s are passed on to the fallback handler.
+pub(crate) struct WarningHandler {
+    plain: bool,
+    start_pos: usize,
+    fallback: Option<Box<dyn TagHandler>>,
+    fallback_factory: Rc<dyn TagHandlerFactory>,
+}
+
+impl TagHandler for WarningHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let is_warning = match tag.data {
+            NodeData::Element { ref attrs, .. } => attrs.borrow().iter().any(|attr| {
+                attr.name.local.to_string() == "class"
+                    && attr
+                        .value
+                        .split_whitespace()
+                        .any(|class| class == "warning")
+            }),
+            _ => false,
+        };
+
+        if is_warning {
+            // Warning should have its own paragraph
+            if !printer.data.ends_with('\n') {
+                printer.insert_newline();
+            }
+            printer.insert_newline();
+            self.start_pos = printer.data.len();
+        } else {
+            let mut fallback = self.fallback_factory.instantiate();
+            fallback.handle(tag, printer);
+            self.fallback = Some(fallback);
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        self.fallback
+            .as_ref()
+            .is_some_and(|fallback| fallback.skip_descendants())
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if let Some(fallback) = &mut self.fallback {
+            fallback.after_handle(printer);
+            return;
+        }
+
+        let content = printer.data.split_off(self.start_pos);
+        if self.plain {
+            printer.append_str("> **Warning**\n>\n");
+        } else {
+            printer.append_str("> [!WARNING]\n");
+        }
+        // Quote every line, collapsing runs of blank lines outside of code blocks
+        let mut in_code_block = false;
+        let mut previous_blank = false;
+        for line in content.trim().lines() {
+            if line.starts_with("```") {
+                in_code_block = !in_code_block;
+            }
+            if line.is_empty() {
+                if !previous_blank || in_code_block {
+                    printer.append_str(">\n");
+                }
+                previous_blank = true;
+            } else {
+                printer.append_str(&format!("> {line}\n"));
+                previous_blank = false;
+            }
+        }
+        printer.insert_newline();
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct WarningHandlerFactory {
+    pub plain: bool,
+    pub fallback: Rc<dyn TagHandlerFactory>,
+}
+
+impl TagHandlerFactory for WarningHandlerFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        Box::new(WarningHandler {
+            plain: self.plain,
+            start_pos: Default::default(),
+            fallback: None,
+            fallback_factory: self.fallback.clone(),
+        })
+    }
+}
@@ -39,5 +39,34 @@ fn test_project(project_path: impl AsRef<Path>) -> Result<()> {
     let extracted_text = fs::read_to_string("EXTRACTED.md")?;
 
     assert_eq!(original_text, extracted_text);
+
+    // Docs were already built, so skipping the build should give the same result
+    assert_cmd::Command::cargo_bin(env!("CARGO_PKG_NAME"))?
+        .arg("doc2readme")
+        .arg("--no-build")
+        .arg("--check")
+        .arg("-o")
+        .arg("README.md")
+        .ok()?;
+
+    // Check mode should pass on the up-to-date README...
+    assert_cmd::Command::cargo_bin(env!("CARGO_PKG_NAME"))?
+        .arg("doc2readme")
+        .arg("--check")
+        .arg("-o")
+        .arg("README.md")
+        .ok()?;
+
+    // ...and fail, without touching the file, on a stale one
+    fs::write("README.md", "stale\n")?;
+    assert_cmd::Command::cargo_bin(env!("CARGO_PKG_NAME"))?
+        .arg("doc2readme")
+        .arg("--check")
+        .arg("-o")
+        .arg("README.md")
+        .assert()
+        .failure();
+    assert_eq!("stale\n", fs::read_to_string("README.md")?);
+
     Ok(())
 }